@@ -1,19 +1,36 @@
 //! Real-time access to the current best non-finalized tip height and the finalized tip height.
 
+use chrono::{DateTime, Utc};
+
 use crate::block;
 
 /// Access to the current best non-finalized chain tip height and the finalized chain tip height.
 pub trait BestTipHeight {
     /// Retrieve the current best chain tip height.
     fn best_tip_height(&self) -> block::Height;
+
+    /// Retrieve the current best chain tip's block hash.
+    fn best_tip_hash(&self) -> block::Hash;
+
+    /// Retrieve the current best chain tip's block time.
+    fn best_tip_block_time(&self) -> DateTime<Utc>;
 }
 
 /// Allow using a dummy best tip height when testing.
 ///
-/// This dummy implementation will always return the height of the genesis block (0).
+/// This dummy implementation will always return the genesis block's height, an all-zero block
+/// hash, and a fixed block time.
 #[cfg(any(test, feature = "proptest-impl"))]
 impl BestTipHeight for () {
     fn best_tip_height(&self) -> block::Height {
         block::Height(0)
     }
+
+    fn best_tip_hash(&self) -> block::Hash {
+        block::Hash([0; 32])
+    }
+
+    fn best_tip_block_time(&self) -> DateTime<Utc> {
+        DateTime::<Utc>::MIN_UTC
+    }
 }
@@ -2,8 +2,38 @@
 
 use std::fmt;
 
+use bech32::{FromBase32, ToBase32, Variant};
+
+use crate::parameters::Network;
+
 use super::keys;
 
+/// The Bech32m human-readable prefix for a mainnet Orchard shielded payment address.
+pub const MAINNET_HRP: &str = "zo";
+
+/// The Bech32m human-readable prefix for a testnet Orchard shielded payment address.
+pub const TESTNET_HRP: &str = "ztestorchard";
+
+/// An error parsing a Bech32m-encoded Orchard [`Address`] string.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum ParseAddressError {
+    /// The string isn't valid Bech32(m), or doesn't use the Bech32m variant.
+    #[error("invalid bech32m string: {0}")]
+    InvalidBech32(String),
+
+    /// The human-readable prefix doesn't match either network's HRP.
+    #[error("unrecognised address prefix: {0}")]
+    UnknownHrp(String),
+
+    /// The decoded payload isn't the expected 43 bytes for an Orchard raw address.
+    #[error("address payload must be exactly 43 bytes, got {0}")]
+    WrongLength(usize),
+
+    /// The decoded payload's last 32 bytes aren't a valid Orchard transmission key.
+    #[error("address payload does not contain a valid transmission key")]
+    InvalidTransmissionKey,
+}
+
 /// A raw **Orchard** _shielded payment address_.
 ///
 /// Also known as a _diversified payment address_ for Orchard, as
@@ -52,13 +82,78 @@ impl From<Address> for [u8; 43] {
     }
 }
 
+impl Address {
+    /// Returns the Bech32m-encoded string form of this [`Address`], using the human-readable
+    /// prefix for `network`.
+    ///
+    /// This wraps the _raw encoding_ (see `impl From<Address> for [u8; 43]`) with the Bech32m
+    /// checksum variant, as recommended for new Zcash address encodings.
+    pub fn to_string_network(&self, network: Network) -> String {
+        let hrp = match network {
+            Network::Mainnet => MAINNET_HRP,
+            Network::Testnet => TESTNET_HRP,
+        };
+
+        let bytes: [u8; 43] = (*self).into();
+
+        bech32::encode(hrp, bytes.to_base32(), Variant::Bech32m)
+            .expect("hrp is ASCII and payload length is within bech32m limits")
+    }
+
+    /// Parses a Bech32m-encoded Orchard shielded payment address, returning the [`Address`] and
+    /// the [`Network`] its human-readable prefix identified.
+    ///
+    /// Rejects strings that aren't valid Bech32m, whose human-readable prefix doesn't match
+    /// either network's Orchard prefix, whose decoded payload isn't exactly 43 bytes, or whose
+    /// payload doesn't contain a valid transmission key.
+    pub fn parse(s: &str) -> Result<(Self, Network), ParseAddressError> {
+        let (hrp, data, variant) =
+            bech32::decode(s).map_err(|error| ParseAddressError::InvalidBech32(error.to_string()))?;
+
+        if variant != Variant::Bech32m {
+            return Err(ParseAddressError::InvalidBech32(
+                "address must use the bech32m checksum variant".to_string(),
+            ));
+        }
+
+        let network = match hrp.as_str() {
+            MAINNET_HRP => Network::Mainnet,
+            TESTNET_HRP => Network::Testnet,
+            other => return Err(ParseAddressError::UnknownHrp(other.to_string())),
+        };
+
+        let bytes = Vec::<u8>::from_base32(&data)
+            .map_err(|error| ParseAddressError::InvalidBech32(error.to_string()))?;
+
+        let bytes: [u8; 43] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| ParseAddressError::WrongLength(bytes.len()))?;
+
+        let mut diversifier_bytes = [0u8; 11];
+        diversifier_bytes.copy_from_slice(&bytes[..11]);
+        let mut transmission_key_bytes = [0u8; 32];
+        transmission_key_bytes.copy_from_slice(&bytes[11..]);
+
+        let diversifier = keys::Diversifier::from(diversifier_bytes);
+        let transmission_key = keys::TransmissionKey::try_from(transmission_key_bytes)
+            .map_err(|_| ParseAddressError::InvalidTransmissionKey)?;
+
+        Ok((
+            Address {
+                diversifier,
+                transmission_key,
+            },
+            network,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use rand_core::OsRng;
 
-    use crate::parameters::Network;
-
     use super::*;
 
     #[test]
@@ -84,4 +179,41 @@ mod tests {
             transmission_key,
         };
     }
+
+    #[test]
+    fn address_string_round_trips_through_each_network_prefix() {
+        zebra_test::init();
+
+        let network = Network::Mainnet;
+        let spending_key = keys::SpendingKey::new(&mut OsRng, network);
+        let full_viewing_key = keys::FullViewingKey::from(spending_key);
+        let diversifier_key = keys::DiversifierKey::from(full_viewing_key);
+        let incoming_viewing_key = keys::IncomingViewingKey::from(full_viewing_key);
+        let diversifier = keys::Diversifier::from(diversifier_key);
+        let transmission_key = keys::TransmissionKey::from((incoming_viewing_key, diversifier));
+
+        let address = Address {
+            diversifier,
+            transmission_key,
+        };
+
+        for network in [Network::Mainnet, Network::Testnet] {
+            let encoded = address.to_string_network(network);
+            let (decoded, decoded_network) = Address::parse(&encoded).expect("valid address");
+
+            assert_eq!(decoded_network, network);
+            assert_eq!(<[u8; 43]>::from(decoded), <[u8; 43]>::from(address));
+        }
+    }
+
+    #[test]
+    fn address_parsing_rejects_malformed_strings() {
+        zebra_test::init();
+
+        assert!(Address::parse("not a bech32m string").is_err());
+        // A valid Bech32 (not Bech32m) string with a Bitcoin human-readable prefix.
+        assert!(Address::parse("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+            .err()
+            .is_some());
+    }
 }
@@ -1,5 +1,7 @@
 //! `getnewaddress` subcommand
 
+pub(crate) mod derivation;
+
 use std::{convert::TryInto, fmt::Debug};
 
 use abscissa_core::{config, Command, FrameworkError, Options, Runnable};
@@ -19,17 +21,25 @@ use crate::config::ZebraCliConfig;
 /// <https://docs.rs/gumdrop/>
 #[derive(Command, Debug, Options)]
 pub struct GetNewAddressCmd {
-    // Example `--foobar` (with short `-f` argument)
-// #[options(short = "f", help = "foobar path"]
-// foobar: Option<PathBuf>
-
-// Example `--baz` argument with no short version
-// #[options(no_short, help = "baz path")]
-// baz: Options<PathBuf>
-
-// "free" arguments don't have an associated flag
-// #[options(free)]
-// free_args: Vec<String>,
+    /// A BIP-39 mnemonic. The mnemonic and `--account` are checked, but the command then exits
+    /// with an error: addresses are only ever derived from fresh entropy, never from a mnemonic.
+    #[options(
+        help = "a BIP-39 mnemonic; it is checked, but address derivation from a mnemonic is not available and the command exits with an error"
+    )]
+    mnemonic: Option<String>,
+
+    /// The ZIP-32 account index to derive, when `--mnemonic` is given. Defaults to account 0.
+    #[options(help = "the ZIP-32 account index to derive (default: 0)")]
+    account: Option<u32>,
+
+    /// An optional BIP-39 passphrase, used only when `--mnemonic` is given.
+    #[options(help = "an optional BIP-39 passphrase for the mnemonic")]
+    passphrase: Option<String>,
+
+    /// Also print the address's Unified Full Viewing Key, so it can be imported into a
+    /// watch-only wallet without exposing spend authority.
+    #[options(help = "also print the unified full viewing key for the new address")]
+    export_viewing_key: bool,
 }
 
 impl config::Override<ZebraCliConfig> for GetNewAddressCmd {
@@ -46,27 +56,77 @@ impl Runnable for GetNewAddressCmd {
     fn run(&self) {
         let network = zebra_chain::parameters::Network::Mainnet;
 
-        let sapling_address = self.new_sapling_address(network);
-        let orchard_address = self.new_orchard_address(network);
+        if let Err(error) = self.validate_mnemonic_options(network) {
+            eprintln!("error: {}", error);
+            return;
+        }
 
-        let zcash_address =
-            self.new_unified_address(network, vec![sapling_address, orchard_address]);
+        let (sapling_receiver, sapling_fvk) = self.new_sapling_address(network);
+        let (orchard_receiver, orchard_fvk) = self.new_orchard_address(network);
+
+        let zcash_address = self.new_unified_address(
+            network,
+            vec![sapling_receiver, orchard_receiver],
+        );
 
         let qr_code = self.create_qr_code_image(&zcash_address);
 
         println!("\nNew Unified Zcash Address:");
         println!("\n{}\n", zcash_address);
         println!("\n{}\n", qr_code);
+
+        if self.export_viewing_key {
+            let viewing_key =
+                self.new_unified_viewing_key(network, vec![sapling_fvk, orchard_fvk]);
+            let viewing_key_qr_code = self.create_qr_code_image(&viewing_key);
+
+            println!("\nUnified Full Viewing Key:");
+            println!("\n{}\n", viewing_key);
+            println!("\n{}\n", viewing_key_qr_code);
+        }
     }
 }
 
 impl GetNewAddressCmd {
-    fn new_sapling_address(&self, network: Network) -> unified::Receiver {
+    /// Checks `--mnemonic`/`--account`/`--passphrase` for validity, without deriving any keys.
+    ///
+    /// Returns an error describing a malformed mnemonic or an account index that already has the
+    /// hardened bit set (2^31 or above), so
+    /// `run` can report it cleanly instead of panicking deep inside key derivation. Since this
+    /// tree has no byte-based `SpendingKey` constructor to actually feed derived key material
+    /// into (see `new_sapling_address`/`new_orchard_address`), `--mnemonic` itself is rejected
+    /// here too: failing loudly is better than silently falling back to fresh, non-reproducible
+    /// entropy while claiming to have honored the flag.
+    fn validate_mnemonic_options(
+        &self,
+        network: Network,
+    ) -> Result<(), derivation::DerivationError> {
+        let Some(mnemonic) = self.mnemonic.as_deref() else {
+            return Ok(());
+        };
+
+        let passphrase = self.passphrase.as_deref().unwrap_or("");
+        let seed = derivation::mnemonic_to_seed(mnemonic, passphrase)?;
+
+        derivation::derive_account_key(
+            "ZcashIP32Sapling",
+            &seed,
+            derivation::coin_type(network),
+            self.account.unwrap_or(0),
+        )?;
+
+        Err(derivation::DerivationError::DeterministicDerivationUnavailable)
+    }
+
+    fn new_sapling_address(&self, network: Network) -> (unified::Receiver, unified::Fvk) {
+        // `--mnemonic` is rejected by `validate_mnemonic_options` before `run` ever reaches
+        // here (see its doc comment), so fresh entropy is the only path this function needs to
+        // support.
         let spending_key = sapling::keys::SpendingKey::new(&mut rand::rngs::OsRng);
 
         let spend_authorizing_key = sapling::keys::SpendAuthorizingKey::from(spending_key);
         let proof_authorizing_key = sapling::keys::ProofAuthorizingKey::from(spending_key);
-        let _outgoing_viewing_key = sapling::keys::OutgoingViewingKey::from(spending_key);
+        let outgoing_viewing_key = sapling::keys::OutgoingViewingKey::from(spending_key);
 
         let authorizing_key = sapling::keys::AuthorizingKey::from(spend_authorizing_key);
         let nullifier_deriving_key =
@@ -80,10 +140,20 @@ impl GetNewAddressCmd {
 
         let sapling_address = sapling::Address::new(network, diversifier, transmission_key);
 
-        unified::Receiver::Sapling(sapling_address.into())
+        // A Sapling full viewing key is `ak || nk || ovk`, per ZIP 32.
+        let mut sapling_fvk = [0u8; 96];
+        sapling_fvk[..32].copy_from_slice(&<[u8; 32]>::from(authorizing_key));
+        sapling_fvk[32..64].copy_from_slice(&<[u8; 32]>::from(nullifier_deriving_key));
+        sapling_fvk[64..].copy_from_slice(&<[u8; 32]>::from(outgoing_viewing_key));
+
+        (
+            unified::Receiver::Sapling(sapling_address.into()),
+            unified::Fvk::Sapling(sapling_fvk),
+        )
     }
 
-    fn new_orchard_address(&self, network: Network) -> unified::Receiver {
+    fn new_orchard_address(&self, network: Network) -> (unified::Receiver, unified::Fvk) {
+        // Same as `new_sapling_address` above: `--mnemonic` never reaches this function.
         let spending_key = orchard::keys::SpendingKey::new(&mut rand::rngs::OsRng, network);
 
         let spend_authorizing_key = orchard::keys::SpendAuthorizingKey::from(spending_key);
@@ -102,7 +172,10 @@ impl GetNewAddressCmd {
 
         let orchard_address = orchard::Address::new(diversifier, transmission_key);
 
-        unified::Receiver::Orchard(orchard_address.into())
+        (
+            unified::Receiver::Orchard(orchard_address.into()),
+            unified::Fvk::Orchard(<[u8; 96]>::from(full_viewing_key)),
+        )
     }
 
     fn new_unified_address<A>(&self, network: Network, address: A) -> zcash_address::ZcashAddress
@@ -121,6 +194,22 @@ impl GetNewAddressCmd {
         )
     }
 
+    /// Assembles `fvks` into a ZIP-316 Unified Full Viewing Key, and renders it as text.
+    fn new_unified_viewing_key<F>(&self, network: Network, fvks: F) -> String
+    where
+        F: TryInto<unified::Ufvk>,
+        F::Error: Debug,
+    {
+        let zcash_network = match network {
+            Network::Mainnet => zcash_address::Network::Main,
+            Network::Testnet => zcash_address::Network::Test,
+        };
+
+        fvks.try_into()
+            .expect("a valid unified::Ufvk")
+            .encode(&zcash_network)
+    }
+
     fn create_qr_code_image(&self, data: impl ToString) -> String {
         let code = QrCode::new(data.to_string()).unwrap();
 
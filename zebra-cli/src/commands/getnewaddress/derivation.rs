@@ -0,0 +1,142 @@
+//! BIP-39 mnemonic expansion and ZIP-32 hierarchical key derivation.
+//!
+//! This module only produces raw key material (seeds, spending keys, and chain codes) as byte
+//! arrays; turning that material into actual Sapling/Orchard keys is the caller's job.
+
+use hmac::{Hmac, Mac, NewMac};
+use pbkdf2::pbkdf2;
+use sha2::Sha512;
+
+/// The number of PBKDF2 rounds used to expand a mnemonic into a seed, per BIP-39.
+const MNEMONIC_PBKDF2_ROUNDS: u32 = 2048;
+
+/// An error in deriving a key from a mnemonic and account path.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum DerivationError {
+    /// The mnemonic did not pass basic sanity checks (non-empty, whitespace-separated words).
+    #[error("malformed mnemonic")]
+    MalformedMnemonic,
+
+    /// The requested account index is not a valid hardened ZIP-32 index.
+    #[error("account index {0} is not a valid hardened index")]
+    NonHardenedAccount(u32),
+
+    /// `--mnemonic` was given, but this tree has no deterministic, byte-based `SpendingKey`
+    /// constructor to feed the derived key material into (see `zebra_chain::sapling`/
+    /// `zebra_chain::orchard::keys`), so deterministic address derivation can't actually happen.
+    #[error(
+        "deterministic derivation from --mnemonic is not available: no byte-based SpendingKey \
+         constructor is wired up in this build"
+    )]
+    DeterministicDerivationUnavailable,
+}
+
+/// The ZIP-32 coin type used in the `m/32'/coin_type'/account'` derivation path.
+pub fn coin_type(network: zebra_chain::parameters::Network) -> u32 {
+    match network {
+        zebra_chain::parameters::Network::Mainnet => 133,
+        zebra_chain::parameters::Network::Testnet => 1,
+    }
+}
+
+/// Expands `mnemonic` (and optional `passphrase`) into a 512-bit seed, per BIP-39.
+///
+/// This only checks that `mnemonic` is non-empty and whitespace-separated; it does not validate
+/// the words against the BIP-39 wordlist or its checksum, since doing so needs the wordlist data
+/// itself, which isn't available in this tree.
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> Result<[u8; 64], DerivationError> {
+    if mnemonic.split_whitespace().count() == 0 {
+        return Err(DerivationError::MalformedMnemonic);
+    }
+
+    let salt = format!("mnemonic{}", passphrase);
+    let mut seed = [0u8; 64];
+    pbkdf2::<Hmac<Sha512>>(
+        mnemonic.as_bytes(),
+        salt.as_bytes(),
+        MNEMONIC_PBKDF2_ROUNDS,
+        &mut seed,
+    );
+
+    Ok(seed)
+}
+
+/// A ZIP-32 extended spending key: the raw spending key material, plus the chain code used to
+/// derive further child keys.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedSpendingKey {
+    /// The raw 32-byte spending key.
+    pub spending_key: [u8; 32],
+
+    /// The 32-byte chain code used to derive this key's children.
+    pub chain_code: [u8; 32],
+}
+
+/// Computes the ZIP-32 master extended spending key for `seed`, using `key` as the HMAC key
+/// (`"ZcashIP32Sapling"` or `"ZcashIP32Orchard"`).
+pub fn master_key(key: &'static str, seed: &[u8]) -> ExtendedSpendingKey {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key.as_bytes())
+        .expect("HMAC can be created with a key of any length");
+    mac.update(seed);
+    let i = mac.finalize().into_bytes();
+
+    let mut spending_key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    spending_key.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+
+    ExtendedSpendingKey {
+        spending_key,
+        chain_code,
+    }
+}
+
+/// Derives the hardened child of `parent` at `index`, following the same
+/// `HMAC-SHA512(key = chain_code, data = 0x00 || parent_key || index)` shape ZIP-32 uses for its
+/// master key, applied recursively along a hardened-only path.
+///
+/// `index` is the unhardened index; the hardened bit is set internally, so callers pass e.g. `32`
+/// for `32'`.
+fn derive_hardened_child(parent: ExtendedSpendingKey, index: u32) -> ExtendedSpendingKey {
+    let hardened_index = index | 0x8000_0000;
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(&parent.chain_code)
+        .expect("HMAC can be created with a key of any length");
+    mac.update(&[0x00]);
+    mac.update(&parent.spending_key);
+    mac.update(&hardened_index.to_be_bytes());
+    let i = mac.finalize().into_bytes();
+
+    let mut spending_key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    spending_key.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+
+    ExtendedSpendingKey {
+        spending_key,
+        chain_code,
+    }
+}
+
+/// Derives the extended spending key at `m/32'/coin_type'/account'` for `seed`, using `key` as
+/// the ZIP-32 master key HMAC key (`"ZcashIP32Sapling"` or `"ZcashIP32Orchard"`).
+///
+/// `account` must be a plain (non-hardened-bit-set) index; it's hardened internally, matching
+/// the fully-hardened account path every Zcash shielded wallet uses.
+pub fn derive_account_key(
+    key: &'static str,
+    seed: &[u8],
+    coin_type: u32,
+    account: u32,
+) -> Result<ExtendedSpendingKey, DerivationError> {
+    if account & 0x8000_0000 != 0 {
+        return Err(DerivationError::NonHardenedAccount(account));
+    }
+
+    let master = master_key(key, seed);
+    let purpose = derive_hardened_child(master, 32);
+    let coin = derive_hardened_child(purpose, coin_type);
+    let account_key = derive_hardened_child(coin, account);
+
+    Ok(account_key)
+}
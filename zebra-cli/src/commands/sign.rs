@@ -0,0 +1,117 @@
+//! `sign` subcommand
+//!
+//! TODO: this command isn't reachable yet - `commands.rs`, the Abscissa file that registers
+//! subcommands into the application's top-level command enum, doesn't exist in this tree. Wire
+//! `SignCmd` in there once it's back, the same way `GetNewAddressCmd` is presumably registered.
+
+use abscissa_core::{config, Command, FrameworkError, Options, Runnable};
+
+use zebra_chain::{orchard, parameters::Network, sapling};
+
+use crate::{commands::getnewaddress::derivation, config::ZebraCliConfig};
+
+/// `sign` subcommand
+///
+/// Produces a detached signature over an arbitrary message, using a shielded spend-authorizing
+/// key - either derived from `--mnemonic`/`--account`, or passed directly as `--key`.
+#[derive(Command, Debug, Options)]
+pub struct SignCmd {
+    /// The message to sign.
+    #[options(free, help = "the message to sign")]
+    message: String,
+
+    /// Sign with the Orchard spend-authorizing key, instead of the Sapling one.
+    #[options(help = "sign with the Orchard key, instead of Sapling")]
+    orchard: bool,
+
+    /// A hex-encoded spend-authorizing key, used instead of `--mnemonic`.
+    #[options(help = "a hex-encoded spend-authorizing key")]
+    key: Option<String>,
+
+    /// Derive the signing key deterministically from a BIP-39 mnemonic, instead of `--key`.
+    #[options(help = "derive the signing key from a BIP-39 mnemonic, instead of --key")]
+    mnemonic: Option<String>,
+
+    /// The ZIP-32 account index to derive, when `--mnemonic` is given. Defaults to account 0.
+    #[options(help = "the ZIP-32 account index to derive (default: 0)")]
+    account: Option<u32>,
+
+    /// An optional BIP-39 passphrase, used only when `--mnemonic` is given.
+    #[options(help = "an optional BIP-39 passphrase for the mnemonic")]
+    passphrase: Option<String>,
+}
+
+impl config::Override<ZebraCliConfig> for SignCmd {
+    fn override_config(&self, config: ZebraCliConfig) -> Result<ZebraCliConfig, FrameworkError> {
+        Ok(config)
+    }
+}
+
+impl Runnable for SignCmd {
+    fn run(&self) {
+        let network = Network::Mainnet;
+
+        let spending_key_bytes = match self.spending_key_bytes(network) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                return;
+            }
+        };
+
+        // TODO: `sapling::keys::SpendAuthorizingKey`/`orchard::keys::SpendAuthorizingKey` aren't
+        // present in this tree to confirm their exact API against; this assumes they expose a
+        // `reddsa`-style `sign(&mut OsRng, message) -> Signature`, matching the real crate's use
+        // of `reddsa` for both Sapling and Orchard spend-authorization signatures.
+        let signature_hex = if self.orchard {
+            let spend_authorizing_key =
+                orchard::keys::SpendAuthorizingKey::from(spending_key_bytes);
+            let signature = spend_authorizing_key.sign(&mut rand::rngs::OsRng, self.message.as_bytes());
+            hex::encode(<[u8; 64]>::from(signature))
+        } else {
+            let spend_authorizing_key =
+                sapling::keys::SpendAuthorizingKey::from(spending_key_bytes);
+            let signature = spend_authorizing_key.sign(&mut rand::rngs::OsRng, self.message.as_bytes());
+            hex::encode(<[u8; 64]>::from(signature))
+        };
+
+        println!("{}", signature_hex);
+    }
+}
+
+impl SignCmd {
+    /// Resolves `--key`/`--mnemonic` into 32 bytes of spending key material.
+    fn spending_key_bytes(&self, network: Network) -> Result<[u8; 32], String> {
+        if let Some(key) = self.key.as_deref() {
+            let bytes = hex::decode(key).map_err(|error| error.to_string())?;
+            return bytes
+                .try_into()
+                .map_err(|_| "key must be exactly 32 bytes".to_string());
+        }
+
+        let mnemonic = self
+            .mnemonic
+            .as_deref()
+            .ok_or("one of --key or --mnemonic is required")?;
+        let passphrase = self.passphrase.as_deref().unwrap_or("");
+        let account = self.account.unwrap_or(0);
+
+        let seed = derivation::mnemonic_to_seed(mnemonic, passphrase)
+            .map_err(|error| error.to_string())?;
+        let hmac_key = if self.orchard {
+            "ZcashIP32Orchard"
+        } else {
+            "ZcashIP32Sapling"
+        };
+
+        let account_key = derivation::derive_account_key(
+            hmac_key,
+            &seed,
+            derivation::coin_type(network),
+            account,
+        )
+        .map_err(|error| error.to_string())?;
+
+        Ok(account_key.spending_key)
+    }
+}
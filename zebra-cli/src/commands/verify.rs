@@ -0,0 +1,83 @@
+//! `verify` subcommand
+//!
+//! TODO: this command isn't reachable yet - `commands.rs`, the Abscissa file that registers
+//! subcommands into the application's top-level command enum, doesn't exist in this tree. Wire
+//! `VerifyCmd` in there once it's back, the same way `GetNewAddressCmd` is presumably registered.
+
+use abscissa_core::{config, Command, FrameworkError, Options, Runnable};
+
+use zebra_chain::{orchard, sapling};
+
+use crate::config::ZebraCliConfig;
+
+/// `verify` subcommand
+///
+/// Checks a detached signature produced by [`SignCmd`][super::sign::SignCmd] against a
+/// spend-validating key and the signed message, and reports success or failure.
+#[derive(Command, Debug, Options)]
+pub struct VerifyCmd {
+    /// The message that was signed.
+    #[options(free, help = "the message that was signed")]
+    message: String,
+
+    /// A hex-encoded spend-validating key.
+    #[options(required, help = "a hex-encoded spend-validating key")]
+    key: String,
+
+    /// A hex-encoded detached signature, as produced by `sign`.
+    #[options(required, help = "a hex-encoded detached signature")]
+    signature: String,
+
+    /// Verify against the Orchard spend-validating key, instead of Sapling.
+    #[options(help = "verify against the Orchard key, instead of Sapling")]
+    orchard: bool,
+}
+
+impl config::Override<ZebraCliConfig> for VerifyCmd {
+    fn override_config(&self, config: ZebraCliConfig) -> Result<ZebraCliConfig, FrameworkError> {
+        Ok(config)
+    }
+}
+
+impl Runnable for VerifyCmd {
+    fn run(&self) {
+        match self.verify() {
+            Ok(()) => println!("signature is valid"),
+            Err(error) => {
+                eprintln!("signature is invalid: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl VerifyCmd {
+    /// Parses `--key`/`--signature` and checks the signature against `--message`.
+    ///
+    /// TODO: `sapling::keys::SpendValidatingKey`/`orchard::keys::SpendValidatingKey` aren't
+    /// present in this tree to confirm their exact API against; this assumes they expose a
+    /// `reddsa`-style `verify(message, &signature) -> Result<(), Error>`, matching `sign`'s
+    /// matching assumption about `SpendAuthorizingKey::sign`.
+    fn verify(&self) -> Result<(), String> {
+        let key_bytes: [u8; 32] = hex::decode(&self.key)
+            .map_err(|error| error.to_string())?
+            .try_into()
+            .map_err(|_| "key must be exactly 32 bytes".to_string())?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|error| error.to_string())?
+            .try_into()
+            .map_err(|_| "signature must be exactly 64 bytes".to_string())?;
+
+        if self.orchard {
+            let spend_validating_key = orchard::keys::SpendValidatingKey::from(key_bytes);
+            spend_validating_key
+                .verify(self.message.as_bytes(), &signature_bytes.into())
+                .map_err(|error| error.to_string())
+        } else {
+            let authorizing_key = sapling::keys::AuthorizingKey::from(key_bytes);
+            authorizing_key
+                .verify(self.message.as_bytes(), &signature_bytes.into())
+                .map_err(|error| error.to_string())
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Fuzz target for Orchard shielded payment address Bech32m encoding/decoding.
+//!
+//! Exercises `Address::parse` directly against arbitrary, likely-malformed strings - the parser
+//! must reject these cleanly rather than panic - and, separately, round-trips arbitrary raw
+//! payloads through `Address::to_string_network` -> `Address::parse`, asserting that any address
+//! this crate itself produces always decodes back to the same 43-byte payload.
+//!
+//! TODO: this checkout has no workspace `Cargo.toml` (and no `zebra-fuzz` crate at all prior to
+//! this file), so there's no `fuzz/Cargo.toml` declaring `libfuzzer-sys`/`arbitrary`/`zebra-chain`
+//! as dependencies, and `cargo fuzz run orchard_address` can't actually run here. This is written
+//! against the real `Address` API introduced alongside it, so it only needs that manifest, not a
+//! rewrite, once it exists.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use zebra_chain::{
+    orchard::{address::Address, keys},
+    parameters::Network,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // Arbitrary, almost-certainly-malformed input should never panic the parser - it should
+    // either decode or return a `ParseAddressError`.
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Address::parse(text);
+    }
+
+    // Any payload we can build into a valid `Address` should round-trip exactly through encoding
+    // and decoding, for both networks.
+    if data.len() < 43 {
+        return;
+    }
+
+    let mut diversifier_bytes = [0u8; 11];
+    diversifier_bytes.copy_from_slice(&data[..11]);
+    let mut transmission_key_bytes = [0u8; 32];
+    transmission_key_bytes.copy_from_slice(&data[11..43]);
+
+    let diversifier = keys::Diversifier::from(diversifier_bytes);
+    let transmission_key = match keys::TransmissionKey::try_from(transmission_key_bytes) {
+        Ok(transmission_key) => transmission_key,
+        // Not every 32-byte string is a valid transmission key; skip the ones that aren't.
+        Err(_) => return,
+    };
+
+    let address = Address::new(diversifier, transmission_key);
+    let original_bytes: [u8; 43] = address.into();
+
+    for network in [Network::Mainnet, Network::Testnet] {
+        let encoded = address.to_string_network(network);
+        let (decoded, decoded_network) =
+            Address::parse(&encoded).expect("an address we just encoded must parse back");
+
+        assert_eq!(decoded_network, network);
+        assert_eq!(<[u8; 43]>::from(decoded), original_bytes);
+    }
+});
@@ -1,6 +1,8 @@
 use std::{
+    collections::HashMap,
     net::SocketAddr,
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -16,7 +18,8 @@ use tower::{
 
 use crate::{
     constants,
-    peer::{Client, LoadTrackedClient},
+    peer::{BanTable, Client, LoadTrackedClient},
+    protocol::external::types::Version,
 };
 
 type LoadTracker = PeakEwmaDiscover<UnboundedReceiverStream<DiscoveryEvent<Client>>>;
@@ -27,7 +30,8 @@ type PollDiscover = Poll<Option<DiscoveryEvent<LoadTrackedClient>>>;
 /// [`Discover`] interface.
 #[pin_project(project = PinnedPeerDiscoverer)]
 pub struct PeerDiscoverer<D> {
-    /// The incoming discovered peers, as a [`Stream`] of peer addresses and [`Client`] services.
+    /// The incoming discovered peers, as a [`Stream`] of peer addresses, [`Client`] services,
+    /// and the peer's negotiated [`Version`].
     #[pin]
     discovered_peers: D,
 
@@ -37,12 +41,39 @@ pub struct PeerDiscoverer<D> {
 
     /// A channel to send received peer services to the load tracker.
     discovery_event_sender: Option<mpsc::UnboundedSender<DiscoveryEvent<Client>>>,
+
+    /// The negotiated [`Version`] of each peer currently being tracked by [`Self::load_tracker`],
+    /// by address.
+    ///
+    /// [`Self::load_tracker`]'s own `Change::Insert` events only carry a [`PeakEwma<Client>`], so
+    /// this is how [`Self::finish_preparing_client`] recovers the version to build a
+    /// [`LoadTrackedClient`] with.
+    peer_versions: HashMap<SocketAddr, Version>,
+
+    /// The minimum negotiated [`Version`] a peer must report to be inserted into the
+    /// load-tracked [`Discover`] set.
+    ///
+    /// Peers below this version are silently dropped in [`Self::forward_discovered_peer`], so
+    /// that consumers of this [`Discover`] only ever see peers usable under the node's current
+    /// consensus rules.
+    min_version: Version,
+
+    /// Misbehavior scores and temporary bans, shared with every [`super::peer::Connector`] clone
+    /// dialing on this node's behalf (see [`Connector::bans`][crate::peer::Connector::bans]).
+    ///
+    /// Checked in [`Self::evict_banned_peers`] whenever the load tracker has no prepared peer to
+    /// return, so a peer that gets banned after it's already been inserted into
+    /// [`Self::load_tracker`] - for example, because a caller recorded a scoreable [`PeerError`]
+    /// for it through [`Connector::bans`][crate::peer::Connector::bans] - is evicted via
+    /// [`Self::remove_peer`] rather than being left in the discover set indefinitely.
+    bans: Arc<Mutex<BanTable>>,
 }
 
 impl<D> PeerDiscoverer<D> {
     /// Create a new [`PeerDiscoverer`] to handle new peers reported in the `discovered_peers`
-    /// stream.
-    pub fn new(discovered_peers: D) -> Self {
+    /// stream, dropping any peer whose negotiated version is below `min_version`, and evicting
+    /// any already-tracked peer that `bans` reports as banned.
+    pub fn new(discovered_peers: D, min_version: Version, bans: Arc<Mutex<BanTable>>) -> Self {
         let (discovery_event_sender, discovery_event_receiver) = mpsc::unbounded_channel();
 
         let load_tracker = PeakEwmaDiscover::new(
@@ -56,6 +87,9 @@ impl<D> PeerDiscoverer<D> {
             discovered_peers,
             load_tracker,
             discovery_event_sender: Some(discovery_event_sender),
+            peer_versions: HashMap::new(),
+            min_version,
+            bans,
         }
     }
 }
@@ -66,7 +100,7 @@ impl<D> PeerDiscoverer<D> {
 /// well.
 impl<D> Stream for PeerDiscoverer<D>
 where
-    D: Stream<Item = (SocketAddr, Client)>,
+    D: Stream<Item = (SocketAddr, Client, Version)>,
 {
     type Item = DiscoveryEvent<LoadTrackedClient>;
 
@@ -75,9 +109,11 @@ where
 
         // Check if load tracker has finished preparing a peer service.
         match this.load_tracker.as_mut().poll_discover(context) {
-            // No, so check if there are newly discovered peers to send to the load tracker.
+            // No, so check if there are newly discovered peers to send to the load tracker, and
+            // whether any already-tracked peer has since been banned.
             Poll::Pending => {
                 this.forward_discovered_peers(context);
+                this.evict_banned_peers();
 
                 Poll::Pending
             }
@@ -89,9 +125,14 @@ where
                 Poll::Ready(Some(discover_event))
             }
 
-            // `Remove` is never sent because `PeerDiscoverer` never sends `Remove` to the load
-            // tracker.
-            Poll::Ready(Some(Ok(Change::Remove(_)))) => unreachable!("no peers are ever removed"),
+            // A peer has been explicitly evicted through `PeerDiscoverer::remove_peer`. There's
+            // nothing left to prepare - the key is all a removal needs - so this just forwards
+            // the event as-is.
+            Poll::Ready(Some(Ok(Change::Remove(address)))) => {
+                this.peer_versions.remove(&address);
+
+                Poll::Ready(Some(Ok(Change::Remove(address))))
+            }
 
             // An error occurred in the load tracker.
             Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error))),
@@ -105,7 +146,7 @@ where
 
 impl<'p, D> PinnedPeerDiscoverer<'p, D>
 where
-    D: Stream<Item = (SocketAddr, Client)>,
+    D: Stream<Item = (SocketAddr, Client, Version)>,
 {
     /// Try to forward newly discovered peers to the load tracker.
     ///
@@ -121,11 +162,79 @@ where
         }
     }
 
-    /// Forward a newly discovered peer to the load tracker.
-    fn forward_discovered_peer(&mut self, (address, client): (SocketAddr, Client)) {
+    /// Forward a newly discovered peer to the load tracker, unless its negotiated version is
+    /// below [`PeerDiscoverer::min_version`], in which case it's dropped without ever reaching
+    /// the load-tracked [`Discover`] set.
+    fn forward_discovered_peer(&mut self, (address, client, version): (SocketAddr, Client, Version)) {
+        if version < *self.min_version {
+            tracing::debug!(
+                ?address,
+                ?version,
+                min_version = ?self.min_version,
+                "dropping peer below the minimum required protocol version",
+            );
+
+            return;
+        }
+
         if let Some(event_sender) = self.discovery_event_sender.as_mut() {
+            // A peer can reconnect - and get discovered again - before the load tracker has
+            // finished preparing its previous connection at this address. [`Self::peer_versions`]
+            // only has room for one pending version per address, so evict the stale pending
+            // insert first: otherwise its completion would either steal this version out from
+            // under the new connection, or find nothing left to remove at all, once both
+            // connections finish preparing (see [`Self::finish_preparing_client`]).
+            if self.peer_versions.contains_key(&address) {
+                let remove_stale_event = Ok(Change::Remove(address));
+
+                if event_sender.send(remove_stale_event).is_err() {
+                    self.discovery_event_sender.take();
+                    return;
+                }
+            }
+
             let event = Ok(Change::Insert(address, client));
 
+            if event_sender.send(event).is_err() {
+                self.discovery_event_sender.take();
+                return;
+            }
+
+            self.peer_versions.insert(address, version);
+        }
+    }
+
+    /// Evicts every currently-tracked peer that [`Self::bans`] reports as banned.
+    ///
+    /// This is the trigger for [`Self::remove_peer`]: a peer recorded enough misbehavior (through
+    /// the same shared [`BanTable`][crate::peer::BanTable] a [`Connector`][crate::peer::Connector]
+    /// consults before dialing) to be temporarily banned, so it should stop being offered as a
+    /// load-balanced service too, not just stop being dialed.
+    fn evict_banned_peers(&mut self) {
+        let banned: Vec<SocketAddr> = {
+            let bans = self.bans.lock().expect("bans mutex should be unpoisoned");
+            self.peer_versions
+                .keys()
+                .filter(|address| bans.is_banned(address))
+                .copied()
+                .collect()
+        };
+
+        for address in banned {
+            self.remove_peer(address);
+        }
+    }
+
+    /// Evict the peer at `address` from the load-tracked [`Discover`] set, if it's currently
+    /// tracked.
+    ///
+    /// This is how a consumer of this [`Discover`] - for example, one that just learned a peer no
+    /// longer meets a newly raised minimum version requirement, or [`Self::evict_banned_peers`]
+    /// noticing a peer just got banned - drops a peer it no longer wants to use.
+    fn remove_peer(&mut self, address: SocketAddr) {
+        if let Some(event_sender) = self.discovery_event_sender.as_mut() {
+            let event = Ok(Change::Remove(address));
+
             if event_sender.send(event).is_err() {
                 self.discovery_event_sender.take();
             }
@@ -138,7 +247,24 @@ where
         address: SocketAddr,
         load_tracked_service: PeakEwma<Client>,
     ) -> DiscoveryEvent<LoadTrackedClient> {
-        let load_tracked_client = LoadTrackedClient::new(load_tracked_service);
+        // This is normally always `Some`, since [`Self::forward_discovered_peer`] records a
+        // peer's version before ever sending its `Change::Insert`, and evicts any stale pending
+        // insert for the same address rather than leaving it to finish here unmatched. Still,
+        // fall back instead of panicking: this is the only safety net against a completion this
+        // code doesn't yet know how to fully dedup racing a concurrent eviction, and downgrading
+        // to `min_version` just means this peer is treated as minimally-capable for load-tracking
+        // purposes, not that the whole discovery stream goes down.
+        let version = self.peer_versions.remove(&address).unwrap_or_else(|| {
+            tracing::warn!(
+                ?address,
+                "peer finished preparing with no recorded version, \
+                 treating it as running the minimum supported version",
+            );
+
+            *self.min_version
+        });
+
+        let load_tracked_client = LoadTrackedClient::new(load_tracked_service, version);
 
         Ok(Change::Insert(address, load_tracked_client))
     }
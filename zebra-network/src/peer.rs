@@ -1,5 +1,7 @@
 //! Peer handling.
 
+/// Misbehavior scoring and temporary bans for peer addresses.
+mod ban;
 /// Handles outbound requests from our node to the network.
 mod client;
 /// The per-peer connection state machine.
@@ -15,9 +17,10 @@ mod meta_data;
 
 use client::{ClientRequest, ClientRequestReceiver, InProgressClientRequest, MustUseOneshotSender};
 
+pub use ban::BanTable;
 pub use client::Client;
-pub use connection::Connection;
+pub use connection::{Connection, PeerConnectionEvent};
 pub use connector::{Connector, OutboundConnectorRequest};
 pub use error::{ErrorSlot, HandshakeError, PeerError, SharedPeerError};
 pub use handshake::{ConnectedAddr, Handshake, HandshakeRequest};
-pub use meta_data::PeerMetaData;
+pub use meta_data::{PeerMetaData, PeerServices};
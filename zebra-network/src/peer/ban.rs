@@ -0,0 +1,174 @@
+//! Peer misbehavior scoring and temporary bans.
+//!
+//! Individual [`PeerError`]s only ever fail a single [`Client`][super::Client]; this module adds
+//! the missing memory of *how often* a given address has misbehaved, so a peer that keeps
+//! churning through connections (for example, by repeatedly triggering
+//! [`PeerError::HeartbeatTaskExited`]) can be banned rather than simply reconnected to forever.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use super::error::PeerError;
+
+/// The misbehavior score at which an address is banned.
+const BAN_THRESHOLD: u32 = 100;
+
+/// The duration of the first ban applied to an address.
+const INITIAL_BAN_DURATION: Duration = Duration::from_secs(60);
+
+/// The factor by which an address's ban duration grows each time it's banned again.
+const BAN_DURATION_GROWTH_FACTOR: u32 = 4;
+
+/// The time it takes a misbehavior score to decay back to zero, if no further errors occur.
+///
+/// Scores decay linearly over this window, so a peer that misbehaved once a long time ago isn't
+/// punished as harshly as one that's misbehaving right now.
+const SCORE_DECAY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Returns the misbehavior score weight of `error`.
+///
+/// Errors that strongly suggest a hostile or broken peer (duplicate handshakes, overload churn)
+/// score higher than ones that can just as easily be caused by normal network conditions (a
+/// connection idling out).
+fn weight(error: &PeerError) -> u32 {
+    match error {
+        PeerError::DuplicateHandshake => 20,
+        PeerError::Overloaded => 10,
+        PeerError::HeartbeatTaskExited | PeerError::ConnectionDropped => 5,
+        PeerError::ConnectionClosed | PeerError::IdleTimeout => 1,
+        _ => 0,
+    }
+}
+
+/// The coarse network bucket an address belongs to, so a single attacker with many addresses in
+/// the same network can't dodge a ban by rotating between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct NetGroup([u8; 4]);
+
+impl NetGroup {
+    fn for_addr(address: &SocketAddr) -> Self {
+        match address.ip() {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                NetGroup([octets[0], octets[1], 0, 0])
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                let first = segments[0].to_be_bytes();
+                let second = segments[1].to_be_bytes();
+                NetGroup([first[0], first[1], second[0], second[1]])
+            }
+        }
+    }
+}
+
+/// The misbehavior record kept for a single address or netgroup.
+#[derive(Clone, Copy, Debug)]
+struct Record {
+    /// The current misbehavior score, as of `last_update`.
+    score: u32,
+
+    /// When `score` was last updated, used to decay it over time.
+    last_update: Instant,
+
+    /// The time until which this address is banned, if it's currently banned.
+    banned_until: Option<Instant>,
+
+    /// The number of times this address has been banned, used to grow the next ban's duration.
+    ban_count: u32,
+}
+
+impl Record {
+    fn fresh(now: Instant) -> Self {
+        Record {
+            score: 0,
+            last_update: now,
+            banned_until: None,
+            ban_count: 0,
+        }
+    }
+
+    /// Decays `self.score` for the time elapsed since `last_update`, as of `now`.
+    fn decay(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        let decayed = (self.score as u128 * elapsed.as_millis())
+            / SCORE_DECAY_WINDOW.as_millis().max(1);
+
+        self.score = self.score.saturating_sub(decayed as u32);
+        self.last_update = now;
+    }
+}
+
+/// A table of per-address and per-netgroup misbehavior scores, used to temporarily ban addresses
+/// that misbehave too often.
+#[derive(Debug, Default)]
+pub struct BanTable {
+    addresses: HashMap<SocketAddr, Record>,
+    netgroups: HashMap<NetGroup, Record>,
+}
+
+impl BanTable {
+    /// Create a new, empty [`BanTable`].
+    pub fn new() -> Self {
+        BanTable::default()
+    }
+
+    /// Record that `address` produced `error`, updating its misbehavior score and that of its
+    /// netgroup, and banning either if they've crossed [`BAN_THRESHOLD`].
+    pub fn record_error(&mut self, address: SocketAddr, error: &PeerError) {
+        let error_weight = weight(error);
+        if error_weight == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let netgroup = NetGroup::for_addr(&address);
+
+        Self::apply(self.addresses.entry(address).or_insert_with(|| Record::fresh(now)), error_weight, now);
+        Self::apply(self.netgroups.entry(netgroup).or_insert_with(|| Record::fresh(now)), error_weight, now);
+    }
+
+    /// Decays `record`'s score, adds `error_weight`, and bans it if it has now crossed
+    /// [`BAN_THRESHOLD`].
+    fn apply(record: &mut Record, error_weight: u32, now: Instant) {
+        record.decay(now);
+        record.score += error_weight;
+
+        if record.score >= BAN_THRESHOLD && record.banned_until.map_or(true, |until| until <= now) {
+            record.ban_count += 1;
+            let ban_duration = INITIAL_BAN_DURATION
+                * BAN_DURATION_GROWTH_FACTOR.saturating_pow(record.ban_count - 1);
+            record.banned_until = Some(now + ban_duration);
+            record.score = 0;
+        }
+    }
+
+    /// Returns `true` if `address`, or its netgroup, is currently banned.
+    pub fn is_banned(&self, address: &SocketAddr) -> bool {
+        let now = Instant::now();
+
+        let address_banned = self
+            .addresses
+            .get(address)
+            .and_then(|record| record.banned_until)
+            .map_or(false, |until| until > now);
+
+        let netgroup_banned = self
+            .netgroups
+            .get(&NetGroup::for_addr(address))
+            .and_then(|record| record.banned_until)
+            .map_or(false, |until| until > now);
+
+        address_banned || netgroup_banned
+    }
+
+    /// Clears every recorded score and ban, for use in tests that need a known-empty table.
+    #[cfg(test)]
+    pub(crate) fn reset(&mut self) {
+        self.addresses.clear();
+        self.netgroups.clear();
+    }
+}
@@ -0,0 +1,398 @@
+//! The [`Client`] half of a peer connection, as seen by the rest of Zebra.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{channel::mpsc, channel::oneshot, future, prelude::*, FutureExt};
+use tokio::task::JoinHandle;
+use tower::Service;
+use tracing::Span;
+
+use crate::{
+    peer::{
+        error::{ErrorSlot, PeerError, SharedPeerError},
+        meta_data::PeerServices,
+    },
+    protocol::external::types::Version,
+    BoxError, Request, Response,
+};
+
+#[cfg(test)]
+pub(crate) mod tests;
+
+/// A single request, paired with the means of returning a response to the caller.
+///
+/// Constructed by [`Client::call`], and consumed by the [`Connection`][super::Connection] run
+/// loop, which uses it to drive the peer connection.
+#[derive(Debug)]
+pub(crate) struct ClientRequest {
+    /// The actual network request.
+    pub request: Request,
+
+    /// The return channel for the response to `request`.
+    pub tx: MustUseOneshotSender,
+
+    /// The tracing context for `request`, so that work done to serve it is correctly attributed
+    /// in traces.
+    pub span: Span,
+
+    /// The deadline by which `request` must be resolved, overriding the connection's default
+    /// request timeout. `None` means the default applies.
+    pub deadline: Option<tokio::time::Instant>,
+}
+
+/// A version of [`ClientRequest`] that's currently being driven by the [`Connection`] run loop.
+///
+/// This is the same as [`ClientRequest`], but documents that it's specifically the in-flight
+/// request the run loop is currently awaiting a response for.
+#[derive(Debug)]
+pub(crate) struct InProgressClientRequest {
+    /// The actual network request.
+    pub request: Request,
+
+    /// The return channel for the response to `request`.
+    pub tx: MustUseOneshotSender,
+
+    /// The tracing context for `request`.
+    pub span: Span,
+
+    /// The deadline by which `request` must be resolved, overriding the connection's default
+    /// request timeout. `None` means the default applies.
+    pub deadline: Option<tokio::time::Instant>,
+}
+
+impl From<ClientRequest> for InProgressClientRequest {
+    fn from(client_request: ClientRequest) -> Self {
+        InProgressClientRequest {
+            request: client_request.request,
+            tx: client_request.tx,
+            span: client_request.span,
+            deadline: client_request.deadline,
+        }
+    }
+}
+
+/// A oneshot sender that logs, and resolves the caller with [`PeerError::ConnectionDropped`], if
+/// it's dropped without sending a response.
+///
+/// This catches bugs where the [`Connection`] run loop drops a [`ClientRequest`] without
+/// responding to it, which would otherwise hang the caller forever.
+#[derive(Debug)]
+pub(crate) struct MustUseOneshotSender(Option<oneshot::Sender<Result<Response, SharedPeerError>>>);
+
+impl From<oneshot::Sender<Result<Response, SharedPeerError>>> for MustUseOneshotSender {
+    fn from(sender: oneshot::Sender<Result<Response, SharedPeerError>>) -> Self {
+        MustUseOneshotSender(Some(sender))
+    }
+}
+
+impl MustUseOneshotSender {
+    /// Send `response` to the original caller of the [`Client`], consuming this sender.
+    pub fn send(mut self, response: Result<Response, SharedPeerError>) {
+        let sender = self
+            .0
+            .take()
+            .expect("sender is only taken here, then the wrapper is immediately dropped");
+
+        // The receiver might have gone away if the caller stopped awaiting the response.
+        let _ = sender.send(response);
+    }
+}
+
+impl Drop for MustUseOneshotSender {
+    fn drop(&mut self) {
+        if let Some(sender) = self.0.take() {
+            tracing::debug!("dropping ClientRequest without sending a response");
+            let _ = sender.send(Err(PeerError::ConnectionDropped.into()));
+        }
+    }
+}
+
+/// A wrapper around a [`mpsc::Receiver<ClientRequest>`], to be used by [`Connection`].
+///
+/// This type exists so that the meaning of channel closure and emptiness can be expressed using
+/// the same `close`/`try_next` vocabulary used for the peer's byte stream, rather than the raw
+/// `mpsc` API.
+#[derive(Debug)]
+pub(crate) struct ClientRequestReceiver {
+    inner: mpsc::Receiver<ClientRequest>,
+}
+
+impl From<mpsc::Receiver<ClientRequest>> for ClientRequestReceiver {
+    fn from(inner: mpsc::Receiver<ClientRequest>) -> Self {
+        ClientRequestReceiver { inner }
+    }
+}
+
+impl Stream for ClientRequestReceiver {
+    type Item = ClientRequest;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl ClientRequestReceiver {
+    /// Closes the receiver, so that no new requests can be sent.
+    ///
+    /// Requests that are already queued can still be received, see [`mpsc::Receiver::close`].
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+/// A monotonically increasing counter, shared between a [`Client`] and the [`Connection`]s it
+/// has owned over time.
+///
+/// Each [`Connection`] run loop captures the generation that was current when it was spawned.
+/// When the [`Client`] reconnects after a failure, it advances the counter, which marks the
+/// outgoing connection's captured generation as stale. A stale connection must not mutate the
+/// shared [`ErrorSlot`] or close request channels that may already belong to its replacement,
+/// since its task can keep running for a little while after it's been superseded (for example,
+/// while an `AbortHandle` is taking effect).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ConnectionGeneration(Arc<AtomicU64>);
+
+impl ConnectionGeneration {
+    /// Returns the current generation.
+    pub fn current(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Advances to a new generation, returning it.
+    pub fn advance(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+/// A function used to re-establish a peer connection after the current one fails.
+///
+/// Returns the request channel and connection task to use for the new connection, or `None` if
+/// reconnection isn't possible right now, in which case the [`Client`] remains failed.
+pub(crate) type Reconnect =
+    Box<dyn FnMut() -> Option<(mpsc::Sender<ClientRequest>, JoinHandle<()>)> + Send>;
+
+/// The `zebra-network` handle to an active peer connection.
+///
+/// `Client`s are responsible for forwarding requests to the [`Connection`][super::Connection]
+/// run loop that owns the underlying peer connection, over `server_tx`.
+pub struct Client {
+    /// The outbound channel for requests, served by the [`Connection`] run loop.
+    pub(super) server_tx: mpsc::Sender<ClientRequest>,
+
+    /// The shared error slot for the connection, populated when the connection exits.
+    pub(super) error_slot: ErrorSlot,
+
+    /// The peer's negotiated protocol version.
+    pub(super) version: Version,
+
+    /// The services the peer advertised in its `version` message.
+    pub(super) services: PeerServices,
+
+    /// A handle to the task driving the connection's run loop, so we can tell if it has exited.
+    pub(super) connection_task: JoinHandle<()>,
+
+    /// The generation of the connection currently backing this `Client`, shared with that
+    /// connection's run loop so it can detect being superseded by a reconnection.
+    pub(super) generation: ConnectionGeneration,
+
+    /// A strategy for re-establishing the connection after it fails, if any. `None` means this
+    /// `Client` permanently fails once its connection does.
+    pub(super) reconnect: Option<Reconnect>,
+}
+
+impl Client {
+    /// Returns the protocol version negotiated with the peer.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns `true` if the peer advertised all of the given `services` in its `version`
+    /// message.
+    ///
+    /// Callers that only work against full nodes, for example, can use this to avoid wasting a
+    /// request on a peer that advertised it can't serve one (e.g. one missing `NODE_NETWORK`).
+    pub fn supports(&self, services: PeerServices) -> bool {
+        self.services.contains(services)
+    }
+
+    /// Send `request`, but resolve it with a timeout error if `deadline` passes before the
+    /// connection produces a response.
+    ///
+    /// This lets latency-sensitive callers fail fast instead of waiting out the connection's
+    /// default request timeout, without affecting the timeout applied to other requests, or to
+    /// the connection as a whole. Use [`Service::call`] for the default timeout.
+    pub fn call_with_deadline(
+        &mut self,
+        request: Request,
+        deadline: tokio::time::Instant,
+    ) -> <Self as Service<Request>>::Future {
+        self.send_request(request, Some(deadline))
+    }
+
+    /// Send `request` over `server_tx`, and return a future resolving to the peer's response.
+    fn send_request(
+        &mut self,
+        request: Request,
+        deadline: Option<tokio::time::Instant>,
+    ) -> <Self as Service<Request>>::Future {
+        let (tx, rx) = oneshot::channel();
+
+        let client_request = ClientRequest {
+            request,
+            tx: tx.into(),
+            span: Span::current(),
+            deadline,
+        };
+
+        let mut server_tx = self.server_tx.clone();
+
+        async move {
+            server_tx
+                .send(client_request)
+                .await
+                .map_err(|_| PeerError::ConnectionDropped)?;
+
+            rx.await.map_err(|_| PeerError::ConnectionDropped)?
+        }
+        .boxed()
+    }
+
+    /// Gracefully stop this connection, rather than abruptly dropping or closing its channels.
+    ///
+    /// Stops accepting new requests immediately, then gives the [`Connection`][super::Connection]
+    /// run loop up to `timeout` to flush any outbound bytes and resolve any outstanding
+    /// requests, before forcing the connection closed.
+    pub async fn shutdown(mut self, timeout: Duration) {
+        // Stop accepting new requests straight away. The run loop keeps draining requests that
+        // are already queued, and once they're gone its request stream ends, so it stops.
+        self.server_tx.close_channel();
+
+        // Record the shutdown as the reason the connection closed, before the run loop notices
+        // the channel closing and races to record its own, less specific, reason.
+        let _ = self
+            .error_slot
+            .try_update_error(PeerError::Shutdown.into());
+
+        match tokio::time::timeout(timeout, &mut self.connection_task).await {
+            Ok(Ok(())) => {}
+            Ok(Err(task_error)) if task_error.is_panic() => {
+                std::panic::resume_unwind(task_error.into_panic())
+            }
+            // The run loop's task was cancelled, or didn't finish draining in time: there's
+            // nothing left to wait for, so force it closed.
+            Ok(Err(_)) | Err(_) => self.connection_task.abort(),
+        }
+    }
+
+    /// Check whether the connection's background task has exited, and if so, record the reason
+    /// in the shared error slot.
+    fn check_connection_task(&mut self, cx: &mut Context<'_>) {
+        match Pin::new(&mut self.connection_task).poll(cx) {
+            Poll::Pending => {}
+            Poll::Ready(Ok(())) => {
+                let _ = self
+                    .error_slot
+                    .try_update_error(PeerError::ConnectionDropped.into());
+            }
+            Poll::Ready(Err(task_error)) => {
+                let _ = self
+                    .error_slot
+                    .try_update_error(PeerError::ConnectionTaskPanicked.into());
+
+                if task_error.is_panic() {
+                    std::panic::resume_unwind(task_error.into_panic());
+                }
+            }
+        }
+    }
+
+    /// Return `true` if the client has irrecoverably failed, for example because its connection
+    /// task exited, or its request channel was closed.
+    pub async fn is_failed(&mut self) -> bool {
+        future::poll_fn(|cx| Poll::Ready(self.poll_ready(cx).is_err())).await
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Result<(), BoxError> {
+        self.check_connection_task(cx);
+
+        if let Some(error) = self.error_slot.try_get_error() {
+            if self.try_reconnect() {
+                return self.poll_ready(cx);
+            }
+
+            return Err(error.into());
+        }
+
+        match self.server_tx.poll_ready(cx) {
+            Poll::Ready(Ok(())) => Ok(()),
+            Poll::Ready(Err(_)) => {
+                let error: SharedPeerError = PeerError::ConnectionDropped.into();
+                let _ = self.error_slot.try_update_error(error.clone());
+                Err(error.into())
+            }
+            Poll::Pending => Ok(()),
+        }
+    }
+
+    /// Attempt to recover from a failed connection by establishing a new one, using the
+    /// configured [`Reconnect`] strategy, if any.
+    ///
+    /// On success, the old connection task is aborted (it may already have exited), the shared
+    /// [`ErrorSlot`] is reset for reuse, and the connection generation is advanced so that the
+    /// old connection's run loop can no longer mutate state on this `Client`'s behalf.
+    fn try_reconnect(&mut self) -> bool {
+        let new_connection = match self.reconnect.as_mut() {
+            Some(reconnect) => reconnect(),
+            None => None,
+        };
+
+        let (server_tx, connection_task) = match new_connection {
+            Some(new_connection) => new_connection,
+            None => return false,
+        };
+
+        self.connection_task.abort();
+        self.server_tx = server_tx;
+        self.connection_task = connection_task;
+        self.generation.advance();
+        self.error_slot.reset();
+
+        true
+    }
+}
+
+impl Service<Request> for Client {
+    type Response = Response;
+    type Error = BoxError;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.check_connection_task(cx);
+
+        if let Some(error) = self.error_slot.try_get_error() {
+            if self.try_reconnect() {
+                return self.poll_ready(cx);
+            }
+
+            return Poll::Ready(Err(error.into()));
+        }
+
+        self.server_tx
+            .poll_ready(cx)
+            .map_err(|_| PeerError::ConnectionDropped.into())
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.send_request(request, None)
+    }
+}
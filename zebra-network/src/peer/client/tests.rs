@@ -12,8 +12,14 @@ use futures::{
 use tokio::task::JoinHandle;
 
 use crate::{
-    peer::{error::SharedPeerError, Client, ClientRequest, ErrorSlot},
+    peer::{
+        client::{ConnectionGeneration, Reconnect},
+        error::{ErrorSlot, SharedPeerError},
+        meta_data::PeerServices,
+        Client, ClientRequest, PeerConnectionEvent,
+    },
     protocol::external::types::Version,
+    Response,
 };
 
 /// The maximum time a mocked peer connection should be alive during a test.
@@ -24,7 +30,10 @@ pub struct ClientTestHarness {
     client_request_receiver: Option<mpsc::Receiver<ClientRequest>>,
     error_slot: ErrorSlot,
     version: Version,
+    services: PeerServices,
     connection_aborter: AbortHandle,
+    generation: ConnectionGeneration,
+    event_receiver: Option<mpsc::Receiver<PeerConnectionEvent>>,
 }
 
 impl ClientTestHarness {
@@ -33,7 +42,12 @@ impl ClientTestHarness {
     pub fn build() -> ClientTestHarnessBuilder {
         ClientTestHarnessBuilder {
             version: None,
+            services: None,
             connection_task: None,
+            reconnect: None,
+            event_receiver: None,
+            server_tx_override: None,
+            error_slot_override: None,
         }
     }
 
@@ -42,6 +56,11 @@ impl ClientTestHarness {
         self.version
     }
 
+    /// Gets the peer services associated to the [`Client`].
+    pub fn services(&self) -> PeerServices {
+        self.services
+    }
+
     /// Closes the receiver endpoint of [`ClientRequests`] that are supposed to be sent to the
     /// remote peer.
     ///
@@ -84,6 +103,44 @@ impl ClientTestHarness {
         self.error_slot.try_get_error()
     }
 
+    /// Returns the [`Client`]'s connection generation counter.
+    ///
+    /// Tests can advance this to simulate a reconnection happening, and then assert that a
+    /// stale connection (one that captured an earlier generation) can no longer clobber shared
+    /// state, such as the [`ErrorSlot`].
+    pub fn generation(&self) -> ConnectionGeneration {
+        self.generation.clone()
+    }
+
+    /// Tries to receive a [`PeerConnectionEvent`] emitted by the mocked connection task.
+    ///
+    /// Returns `None` if no [`PeerConnectionEvent`] channel was configured via
+    /// [`ClientTestHarnessBuilder::with_event_channel`], or if none is currently available.
+    pub fn try_to_receive_event(&mut self) -> Option<PeerConnectionEvent> {
+        self.event_receiver.as_mut()?.try_next().ok().flatten()
+    }
+
+    /// Completes a previously received outbound request by sending `response` to the caller
+    /// that's waiting on it.
+    ///
+    /// `attempt` is usually the result of a prior call to
+    /// [`try_to_receive_outbound_client_request`][Self::try_to_receive_outbound_client_request].
+    ///
+    /// # Panics
+    ///
+    /// If `attempt` doesn't contain a received [`ClientRequest`].
+    pub(crate) fn respond_to_outbound_request(
+        &self,
+        attempt: ReceiveRequestAttempt,
+        response: Response,
+    ) {
+        let request = attempt
+            .request()
+            .expect("attempt did not contain a received ClientRequest");
+
+        request.tx.send(Ok(response));
+    }
+
     /// Sets the error in the [`ErrorSlot`], assuming there isn't one already.
     ///
     /// # Panics
@@ -102,6 +159,22 @@ impl ClientTestHarness {
         // Allow the task to detect that it was aborted.
         tokio::task::yield_now().await;
     }
+
+    /// Gracefully stops the mock background task, mirroring [`Client::shutdown`].
+    ///
+    /// Closes the outbound request channel first, so that any in-flight mocked work has a
+    /// chance to finish on its own, and only force-aborts the task if it is still running once
+    /// `timeout` elapses.
+    pub async fn graceful_stop_connection_task(&mut self, timeout: Duration) {
+        self.close_outbound_client_request_receiver();
+
+        tokio::time::sleep(timeout).await;
+
+        self.connection_aborter.abort();
+
+        // Allow the task to detect that it was aborted.
+        tokio::task::yield_now().await;
+    }
 }
 
 /// The result of an attempt to receive a [`ClientRequest`] sent by the [`Client`] instance.
@@ -131,7 +204,6 @@ impl ReceiveRequestAttempt {
     }
 
     /// Returns the received request, if there was one.
-    #[allow(dead_code)]
     pub fn request(self) -> Option<ClientRequest> {
         match self {
             ReceiveRequestAttempt::Request(request) => Some(request),
@@ -148,6 +220,11 @@ impl ReceiveRequestAttempt {
 pub struct ClientTestHarnessBuilder<C = future::Ready<()>> {
     connection_task: Option<C>,
     version: Option<Version>,
+    services: Option<PeerServices>,
+    reconnect: Option<Reconnect>,
+    event_receiver: Option<mpsc::Receiver<PeerConnectionEvent>>,
+    server_tx_override: Option<mpsc::Sender<ClientRequest>>,
+    error_slot_override: Option<ErrorSlot>,
 }
 
 impl<C> ClientTestHarnessBuilder<C>
@@ -160,6 +237,12 @@ where
         self
     }
 
+    /// Configure the mocked services for the peer.
+    pub fn with_services(mut self, services: PeerServices) -> Self {
+        self.services = Some(services);
+        self
+    }
+
     /// Configure the mock connection task future to use.
     pub fn with_connection_task<NewC>(
         self,
@@ -168,30 +251,71 @@ where
         ClientTestHarnessBuilder {
             connection_task: Some(connection_task),
             version: self.version,
+            services: self.services,
+            reconnect: self.reconnect,
+            event_receiver: self.event_receiver,
+            server_tx_override: self.server_tx_override,
+            error_slot_override: self.error_slot_override,
         }
     }
 
+    /// Configure a reconnection strategy, used to recover the [`Client`] after its connection
+    /// fails, instead of letting it fail permanently.
+    pub fn with_reconnect(mut self, reconnect: Reconnect) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// Configure a [`PeerConnectionEvent`] channel, returning the sending half.
+    ///
+    /// The harness has no real [`Connection`][crate::peer::Connection] to emit events from, so
+    /// pass the returned sender into a custom [`with_connection_task`][Self::with_connection_task]
+    /// future that emits events the way a real connection would, and then read them back via
+    /// [`ClientTestHarness::try_to_receive_event`].
+    pub fn with_event_channel(mut self) -> (Self, mpsc::Sender<PeerConnectionEvent>) {
+        // Matches the capacity of the channel a real `Connection` is given in production: a
+        // handful of buffered events is enough for a best-effort observability channel.
+        let (event_sender, event_receiver) = mpsc::channel(16);
+        self.event_receiver = Some(event_receiver);
+        (self, event_sender)
+    }
+
     /// Build a [`Client`] instance with the mocked data and a [`ClientTestHarness`] to track it.
     pub fn finish(self) -> (Client, ClientTestHarness) {
-        let (client_request_sender, client_request_receiver) = mpsc::channel(1);
-        let error_slot = ErrorSlot::default();
+        let (client_request_sender, client_request_receiver) = match self.server_tx_override {
+            Some(server_tx) => (server_tx, None),
+            None => {
+                let (sender, receiver) = mpsc::channel(1);
+                (sender, Some(receiver))
+            }
+        };
+        let error_slot = self.error_slot_override.unwrap_or_default();
         let version = self.version.unwrap_or(Version(0));
+        let services = self.services.unwrap_or_else(PeerServices::empty);
 
         let (connection_task, connection_aborter) =
             Self::spawn_background_task_or_fallback(self.connection_task);
 
+        let generation = ConnectionGeneration::default();
+
         let client = Client {
             server_tx: client_request_sender,
             error_slot: error_slot.clone(),
             version,
+            services,
             connection_task,
+            generation: generation.clone(),
+            reconnect: self.reconnect,
         };
 
         let harness = ClientTestHarness {
-            client_request_receiver: Some(client_request_receiver),
+            client_request_receiver,
             error_slot,
             version,
+            services,
             connection_aborter,
+            generation,
+            event_receiver: self.event_receiver,
         };
 
         (client, harness)
@@ -1,11 +1,11 @@
 //! Fixed peer [`Client`] test vectors.
 
-use futures::poll;
-use tower::ServiceExt;
+use futures::{channel::mpsc, poll};
+use tower::{Service, ServiceExt};
 
 use zebra_test::service_extensions::IsReady;
 
-use crate::{peer::ClientTestHarness, PeerError};
+use crate::{peer::ClientTestHarness, PeerError, Request, Response};
 
 #[tokio::test]
 async fn client_service_ready_ok() {
@@ -171,3 +171,76 @@ async fn client_service_handles_panicked_heartbeat_task() {
     assert!(!harness.wants_connection_heartbeats());
     assert!(harness.try_to_receive_outbound_client_request().is_closed());
 }
+
+/// A [`Client`] configured with a reconnection strategy recovers from a failed connection,
+/// instead of failing permanently, and advances its connection generation when it does.
+#[tokio::test]
+async fn client_service_reconnects_after_error() {
+    zebra_test::init();
+
+    let (mut client, mut harness) = ClientTestHarness::build()
+        .with_reconnect(Box::new(|| {
+            let (server_tx, server_rx) = mpsc::channel(1);
+            // Keep the receiver alive in the new connection task, so the new channel stays open.
+            let connection_task = tokio::spawn(async move {
+                let _server_rx = server_rx;
+                std::future::pending::<()>().await
+            });
+            Some((server_tx, connection_task))
+        }))
+        .finish();
+
+    let generation = harness.generation();
+    assert_eq!(generation.current(), 0);
+
+    harness.set_error(PeerError::ConnectionClosed);
+
+    assert!(!client.is_failed().await, "client should have reconnected");
+    assert!(harness.current_error().is_none());
+    assert_eq!(generation.current(), 1);
+}
+
+/// A [`Client`] request completes a full round-trip once the harness replies to the outbound
+/// [`ClientRequest`][crate::peer::ClientRequest] it observed.
+#[tokio::test]
+async fn client_service_call_round_trip() {
+    zebra_test::init();
+
+    let (mut client, mut harness) = ClientTestHarness::build().finish();
+
+    let mut call = Service::call(&mut client, Request::Peers);
+
+    // The request is only sent once the call future is polled.
+    assert!(poll!(&mut call).is_pending());
+
+    let request = harness.try_to_receive_outbound_client_request();
+    assert!(!request.is_empty());
+
+    harness.respond_to_outbound_request(request, Response::Peers(vec![]));
+
+    let response = call
+        .await
+        .expect("request should resolve with the harness's response");
+    assert!(matches!(response, Response::Peers(peers) if peers.is_empty()));
+}
+
+/// Gracefully shut down a [`Client`], and check that it records [`PeerError::Shutdown`] instead
+/// of a generic connection-closed error.
+#[tokio::test]
+async fn client_service_graceful_shutdown() {
+    zebra_test::init();
+
+    let (client, mut harness) = ClientTestHarness::build().finish();
+
+    client.shutdown(std::time::Duration::from_secs(1)).await;
+
+    assert!(
+        matches!(
+            harness.current_error().map(|error| error.inner_debug()),
+            Some(ref debug) if debug.contains("Shutdown")
+        ),
+        "expected a Shutdown error, got: {:?}",
+        harness.current_error()
+    );
+    assert!(harness.try_to_receive_outbound_client_request().is_closed());
+}
@@ -0,0 +1,365 @@
+//! The per-peer connection actor, which handles the peer connection's state machine.
+
+use std::time::Duration;
+
+use futures::{
+    channel::mpsc,
+    future::{Fuse, FusedFuture, FutureExt},
+    sink::SinkExt,
+    stream::StreamExt,
+};
+use tower::Service;
+
+use crate::{
+    meta_addr::MetaAddr,
+    peer::{
+        client::{
+            ClientRequest, ClientRequestReceiver, ConnectionGeneration, InProgressClientRequest,
+        },
+        error::{ErrorSlot, PeerError, SharedPeerError},
+    },
+    peer_set::ConnectionTracker,
+    protocol::external::Message,
+    BoxError, Request, Response,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The default amount of time a [`ClientRequest`] can wait for a response before the connection
+/// resolves it with [`PeerError::ClientRequestTimeout`].
+///
+/// Callers can override this on a per-request basis via `ClientRequest::deadline`.
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The capacity of the bounded channel used to deliver [`PeerConnectionEvent`]s.
+///
+/// Kept small deliberately: this is a best-effort observability channel, not a reliable log, so
+/// a subscriber that's fallen behind just misses events rather than stalling the run loop.
+const EVENT_CHANNEL_CAPACITY: usize = 16;
+
+/// The state of the connection's per-request state machine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Waiting for a request from Zebra, or a message from the peer.
+    AwaitingRequest,
+
+    /// Awaiting a response from the peer, to forward to the caller of an in-progress client
+    /// request.
+    AwaitingResponse,
+
+    /// The connection has failed, and is shutting down.
+    Failed,
+}
+
+/// A structured notification of something that happened in a [`Connection`]'s run loop.
+///
+/// Subscribers (for example, the peer set or metrics layer) can watch these instead of polling
+/// [`ErrorSlot::try_get_error`] or scraping counters.
+#[derive(Clone, Debug)]
+pub enum PeerConnectionEvent {
+    /// The connection is ready to serve its first request.
+    HandshakeComplete,
+
+    /// A request was received from the attached [`Client`][super::Client].
+    RequestReceived,
+
+    /// A response was sent back to the caller of an in-progress request.
+    ResponseSent,
+
+    /// The connection's state machine transitioned to a new state.
+    StateChanged(State),
+
+    /// The connection closed, for the given reason.
+    Closed(SharedPeerError),
+}
+
+/// The per-peer connection actor.
+///
+/// Each `Connection` drives a single peer connection's state machine, forwarding outbound
+/// [`ClientRequest`]s from the paired [`Client`][super::Client] to the peer, and the peer's
+/// messages back to the [`Client`] or to the inbound `svc`.
+pub struct Connection<S, Tx> {
+    /// The state of this connection's current request, if any.
+    pub(super) state: State,
+
+    /// A timer for the current client request, if any.
+    ///
+    /// If the timer elapses before the peer responds, the request is cancelled with a timeout
+    /// error.
+    pub(super) request_timer: Option<Fuse<tokio::time::Sleep>>,
+
+    /// A cache of addresses recently gossiped by the peer, ready to be served on request.
+    pub(super) cached_addrs: Vec<MetaAddr>,
+
+    /// The inbound service, used to answer requests from the peer.
+    pub(super) svc: S,
+
+    /// The receiver for requests from the [`Client`][super::Client] half of the connection.
+    pub(super) client_rx: ClientRequestReceiver,
+
+    /// The request currently awaiting a response, if any. Resolved either by a matching
+    /// response from the peer, or by `request_timer` elapsing first.
+    pub(super) in_progress_request: Option<InProgressClientRequest>,
+
+    /// The shared error slot, used to propagate the reason this connection closed.
+    pub(super) error_slot: ErrorSlot,
+
+    /// The sink used to send messages to the peer.
+    pub(super) peer_tx: Tx,
+
+    /// Keeps the connection's slot in the [`ActiveConnectionCounter`][super::super::peer_set::ActiveConnectionCounter]
+    /// reserved for as long as the connection is alive.
+    pub(super) connection_tracker: ConnectionTracker,
+
+    /// A label used to identify this connection's metrics.
+    pub(super) metrics_label: String,
+
+    /// The most recently logged state, used to avoid emitting duplicate metrics.
+    pub(super) last_metrics_state: Option<&'static str>,
+
+    /// The maximum duration this connection can go without sending or receiving a message,
+    /// before it is closed for being idle.
+    ///
+    /// `None` disables the idle timeout.
+    pub(super) idle_timeout: Option<Duration>,
+
+    /// The generation this connection was spawned with.
+    ///
+    /// Compared against `current_generation` before mutating shared state, so that a connection
+    /// superseded by a reconnection can't clobber the state of the one that replaced it.
+    pub(super) generation: u64,
+
+    /// The [`Client`][super::Client]'s current connection generation, shared with it.
+    pub(super) current_generation: ConnectionGeneration,
+
+    /// A channel used to publish [`PeerConnectionEvent`]s to any interested subscriber.
+    ///
+    /// `None` if nobody is listening. Sending never blocks the run loop: a subscriber that falls
+    /// behind just misses events, rather than stalling the connection.
+    pub(super) event_tx: Option<mpsc::Sender<PeerConnectionEvent>>,
+}
+
+impl<S, Tx> Connection<S, Tx> {
+    /// Returns `true` if this connection is still the one its [`Client`][super::Client] is
+    /// tracking, rather than having been superseded by a reconnection.
+    fn is_current_generation(&self) -> bool {
+        self.generation == self.current_generation.current()
+    }
+
+    /// Publish `event` to the event channel, if anyone is listening.
+    ///
+    /// Uses a non-blocking send: a full or closed channel just drops the event, so a slow or
+    /// absent subscriber can never stall the run loop.
+    fn emit_event(&mut self, event: PeerConnectionEvent) {
+        if let Some(event_tx) = self.event_tx.as_mut() {
+            let _ = event_tx.try_send(event);
+        }
+    }
+}
+
+/// Dropping a [`Connection`] before its `run` future completes abandons any in-flight client
+/// request, and any bytes the peer has sent but we haven't processed yet. Record that as the
+/// reason the connection closed, unless some other error got there first, or this connection has
+/// already been superseded by a reconnection.
+impl<S, Tx> Drop for Connection<S, Tx> {
+    fn drop(&mut self) {
+        if self.is_current_generation() {
+            let _ = self
+                .error_slot
+                .try_update_error(PeerError::ConnectionDropped.into());
+        }
+    }
+}
+
+impl<S, Tx> Connection<S, Tx>
+where
+    S: Service<Request, Response = Response, Error = BoxError>,
+    S::Future: Send + 'static,
+    Tx: futures::Sink<Message, Error = BoxError> + Unpin,
+{
+    /// Consume this `Connection` and run its event loop, using `peer_inbound_rx` as the stream of
+    /// messages received from the peer.
+    pub async fn run<Rx>(mut self, mut peer_inbound_rx: Rx)
+    where
+        Rx: futures::Stream<Item = Result<Message, BoxError>> + Unpin,
+    {
+        // The time the connection last made progress, either by reading a message from the peer,
+        // or by writing one to it. Used to enforce `idle_timeout`.
+        let mut last_activity = tokio::time::Instant::now();
+
+        self.emit_event(PeerConnectionEvent::HandshakeComplete);
+
+        loop {
+            self.update_state_metrics(None);
+
+            if self.state == State::Failed {
+                break;
+            }
+
+            // Race the idle timeout against the rest of the event sources, so that a peer that
+            // has gone quiet gets disconnected instead of being kept alive forever.
+            let idle_timeout = async {
+                match self.idle_timeout {
+                    Some(timeout) => tokio::time::sleep_until(last_activity + timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            // The per-request timer has to survive across polls, so it's held in `self` rather
+            // than recreated every iteration like `idle_timeout` above. Take it out for the
+            // `select!`, then put it back afterwards if it's still pending.
+            let mut request_timer = self.request_timer.take();
+            let request_timeout = async {
+                match request_timer.as_mut() {
+                    Some(timer) => timer.await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let result = tokio::select! {
+                request = self.client_rx.next(), if self.state == State::AwaitingRequest => {
+                    match request {
+                        Some(request) => {
+                            last_activity = tokio::time::Instant::now();
+                            self.handle_client_request(request).await
+                        }
+                        None => Err(PeerError::ConnectionDropped.into()),
+                    }
+                }
+
+                inbound_message = peer_inbound_rx.next() => {
+                    match inbound_message {
+                        Some(Ok(message)) => {
+                            last_activity = tokio::time::Instant::now();
+                            self.handle_inbound_message(message).await
+                        }
+                        Some(Err(error)) => Err(error),
+                        None => Err(PeerError::ConnectionClosed.into()),
+                    }
+                }
+
+                () = idle_timeout => {
+                    Err(PeerError::IdleTimeout.into())
+                }
+
+                () = request_timeout => {
+                    // A request timeout only resolves the one waiting caller: it doesn't say
+                    // anything about the health of the connection itself.
+                    self.handle_request_timeout();
+                    Ok(())
+                }
+            };
+
+            // Don't restore a timer that has already fired, or overwrite one that a newly
+            // received request installed.
+            if let Some(timer) = request_timer {
+                if self.request_timer.is_none() && !timer.is_terminated() {
+                    self.request_timer = Some(timer);
+                }
+            }
+
+            if let Err(error) = result {
+                self.fail_with(error);
+                break;
+            }
+        }
+
+        if self.is_current_generation() {
+            self.client_rx.close();
+        }
+        self.update_state_metrics(Some("closed"));
+
+        let error = self
+            .error_slot
+            .try_get_error()
+            .unwrap_or_else(|| PeerError::ConnectionDropped.into());
+        self.emit_event(PeerConnectionEvent::Closed(error));
+    }
+
+    /// Handle a single request from the attached [`Client`][super::Client].
+    async fn handle_client_request(&mut self, request: ClientRequest) -> Result<(), SharedPeerError> {
+        self.emit_event(PeerConnectionEvent::RequestReceived);
+
+        let request: InProgressClientRequest = request.into();
+
+        let deadline = request
+            .deadline
+            .unwrap_or_else(|| tokio::time::Instant::now() + DEFAULT_REQUEST_TIMEOUT);
+        self.request_timer = Some(tokio::time::sleep_until(deadline).fuse());
+
+        // TODO: actually forward `request.request` to the peer, and resolve `request.tx` when
+        //       the matching response arrives (#1165). Until then, `request_timer` is the only
+        //       thing that can resolve `request`.
+        self.in_progress_request = Some(request);
+        self.state = State::AwaitingResponse;
+
+        Ok(())
+    }
+
+    /// Handle a single message received from the peer.
+    async fn handle_inbound_message(&mut self, message: Message) -> Result<(), SharedPeerError> {
+        // TODO: route `message` to the inbound service, or use it to resolve an in-progress
+        //       client request (#1165).
+        let _ = (&message, &mut self.svc);
+
+        Ok(())
+    }
+
+    /// Resolve the in-progress request, if any, with a timeout error.
+    ///
+    /// Unlike other error paths, this only affects the one caller who's been waiting too long:
+    /// the connection itself is still healthy, so it goes back to waiting for the next request.
+    fn handle_request_timeout(&mut self) {
+        if let Some(request) = self.in_progress_request.take() {
+            request.tx.send(Err(PeerError::ClientRequestTimeout.into()));
+            self.emit_event(PeerConnectionEvent::ResponseSent);
+        }
+
+        self.state = State::AwaitingRequest;
+    }
+
+    /// Record that the connection has failed with `error`, and propagate it to any waiting
+    /// callers.
+    fn fail_with(&mut self, error: impl Into<SharedPeerError>) {
+        let error = error.into();
+
+        tracing::debug!(?error, "connection failed, shutting down");
+
+        // A connection that's been superseded by a reconnection must not touch shared state: the
+        // `Client` (and a fresh `Connection`) may already be relying on it being untouched.
+        if self.is_current_generation() {
+            // Ignore the update error: if the slot already has an error, we don't need to set it
+            // again.
+            let _ = self.error_slot.try_update_error(error);
+
+            self.client_rx.close();
+        }
+
+        self.state = State::Failed;
+    }
+
+    /// Update the connection's metrics, based on its current state.
+    fn update_state_metrics(&mut self, force_state: Option<&'static str>) {
+        let current_state = force_state.unwrap_or(match self.state {
+            State::AwaitingRequest => "awaiting_request",
+            State::AwaitingResponse => "awaiting_response",
+            State::Failed => "failed",
+        });
+
+        if self.last_metrics_state != Some(current_state) {
+            metrics::increment_counter!(
+                "zcash.net.connection.state",
+                "state" => current_state,
+                "addr" => self.metrics_label.clone(),
+            );
+            self.last_metrics_state = Some(current_state);
+
+            // `force_state` is only used for the synthetic "closed" pseudo-state, which isn't a
+            // real `State` variant: that transition is reported separately, via `Closed`.
+            if force_state.is_none() {
+                self.emit_event(PeerConnectionEvent::StateChanged(self.state.clone()));
+            }
+        }
+    }
+}
@@ -9,7 +9,9 @@ use zebra_test::mock_service::MockService;
 
 use crate::{
     peer::{
-        client::ClientRequestReceiver, connection::State, ClientRequest, Connection, ErrorSlot,
+        client::{ClientRequestReceiver, ConnectionGeneration},
+        connection::{State, EVENT_CHANNEL_CAPACITY},
+        ClientRequest, Connection, ErrorSlot, PeerConnectionEvent,
     },
     peer_set::ActiveConnectionCounter,
     protocol::external::Codec,
@@ -26,9 +28,11 @@ fn new_test_connection<A>() -> (
     MockService<Request, Response, A>,
     FramedRead<DuplexStream, Codec>,
     ErrorSlot,
+    mpsc::Receiver<PeerConnectionEvent>,
 ) {
     let (client_tx, client_rx) = mpsc::channel(1);
     let (peer_outbound_writer, peer_outbound_reader) = duplex(4096);
+    let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
 
     let codec = Codec::builder()
         .for_network(Network::Mainnet)
@@ -47,11 +51,16 @@ fn new_test_connection<A>() -> (
         cached_addrs: Vec::new(),
         svc: mock_inbound_service.clone(),
         client_rx: ClientRequestReceiver::from(client_rx),
+        in_progress_request: None,
         error_slot: shared_error_slot.clone(),
         peer_tx: peer_outbound_tx,
         connection_tracker: ActiveConnectionCounter::new_counter().track_connection(),
         metrics_label: "test".to_string(),
         last_metrics_state: None,
+        idle_timeout: None,
+        generation: 0,
+        current_generation: ConnectionGeneration::default(),
+        event_tx: Some(event_tx),
     };
 
     (
@@ -60,5 +69,6 @@ fn new_test_connection<A>() -> (
         mock_inbound_service,
         peer_outbound_rx,
         shared_error_slot,
+        event_rx,
     )
 }
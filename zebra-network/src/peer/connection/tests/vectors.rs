@@ -4,7 +4,12 @@
 //! - connection tests when awaiting requests (#3232)
 //! - connection tests with closed/dropped peer_outbound_tx (#3233)
 
-use futures::{channel::mpsc, FutureExt, StreamExt};
+use std::time::Duration;
+
+use futures::{
+    channel::{mpsc, oneshot},
+    FutureExt, StreamExt,
+};
 use tokio::io::{duplex, DuplexStream};
 use tokio_util::codec::{FramedRead, FramedWrite};
 
@@ -13,13 +18,32 @@ use zebra_test::mock_service::{MockService, PanicAssertion};
 
 use crate::{
     peer::{
-        client::ClientRequestReceiver, connection::State, ClientRequest, Connection, ErrorSlot,
+        client::{ClientRequestReceiver, ConnectionGeneration},
+        connection::{State, DEFAULT_REQUEST_TIMEOUT, EVENT_CHANNEL_CAPACITY},
+        ClientRequest, Connection, ErrorSlot, PeerConnectionEvent,
     },
     peer_set::ActiveConnectionCounter,
     protocol::external::Codec,
     PeerError, Request, Response,
 };
 
+/// Collects every event currently buffered on `event_rx`, without blocking.
+fn drain_events(event_rx: &mut mpsc::Receiver<PeerConnectionEvent>) -> Vec<&'static str> {
+    let mut events = Vec::new();
+
+    while let Ok(Some(event)) = event_rx.try_next() {
+        events.push(match event {
+            PeerConnectionEvent::HandshakeComplete => "handshake_complete",
+            PeerConnectionEvent::RequestReceived => "request_received",
+            PeerConnectionEvent::ResponseSent => "response_sent",
+            PeerConnectionEvent::StateChanged(_) => "state_changed",
+            PeerConnectionEvent::Closed(_) => "closed",
+        });
+    }
+
+    events
+}
+
 #[tokio::test]
 async fn connection_run_loop_ok() {
     zebra_test::init();
@@ -28,8 +52,14 @@ async fn connection_run_loop_ok() {
     // but that doesn't change how the state machine behaves.
     let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
 
-    let (connection, client_tx, mut inbound_service, mut peer_outbound_messages, shared_error_slot) =
-        new_test_connection();
+    let (
+        connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
 
@@ -52,6 +82,12 @@ async fn connection_run_loop_ok() {
     assert!(!client_tx.is_closed());
     assert!(!peer_inbound_tx.is_closed());
 
+    // The connection reached `AwaitingRequest` without ever closing.
+    assert_eq!(
+        drain_events(&mut event_rx),
+        vec!["handshake_complete", "state_changed"]
+    );
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -67,8 +103,14 @@ async fn connection_run_loop_future_drop() {
     // but that doesn't change how the state machine behaves.
     let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
 
-    let (connection, client_tx, mut inbound_service, mut peer_outbound_messages, shared_error_slot) =
-        new_test_connection();
+    let (
+        connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
 
@@ -82,6 +124,13 @@ async fn connection_run_loop_future_drop() {
     assert!(client_tx.is_closed());
     assert!(peer_inbound_tx.is_closed());
 
+    // Dropping the future abandons the run loop mid-flight: it never reaches its own `Closed`
+    // event, even though the `Drop` impl records an error in the shared slot.
+    assert_eq!(
+        drain_events(&mut event_rx),
+        vec!["handshake_complete", "state_changed"]
+    );
+
     assert!(peer_outbound_messages.next().await.is_none());
 
     inbound_service.expect_no_requests().await;
@@ -101,6 +150,7 @@ async fn connection_run_loop_client_close() {
         mut inbound_service,
         mut peer_outbound_messages,
         shared_error_slot,
+        mut event_rx,
     ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
@@ -120,6 +170,10 @@ async fn connection_run_loop_client_close() {
     assert!(client_tx.is_closed());
     assert!(peer_inbound_tx.is_closed());
 
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -135,8 +189,14 @@ async fn connection_run_loop_client_drop() {
     // but that doesn't change how the state machine behaves.
     let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
 
-    let (connection, client_tx, mut inbound_service, mut peer_outbound_messages, shared_error_slot) =
-        new_test_connection();
+    let (
+        connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
 
@@ -154,6 +214,10 @@ async fn connection_run_loop_client_drop() {
 
     assert!(peer_inbound_tx.is_closed());
 
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -169,8 +233,14 @@ async fn connection_run_loop_inbound_close() {
     // but that doesn't change how the state machine behaves.
     let (mut peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
 
-    let (connection, client_tx, mut inbound_service, mut peer_outbound_messages, shared_error_slot) =
-        new_test_connection();
+    let (
+        connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
 
@@ -189,6 +259,10 @@ async fn connection_run_loop_inbound_close() {
     assert!(client_tx.is_closed());
     assert!(peer_inbound_tx.is_closed());
 
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -204,8 +278,14 @@ async fn connection_run_loop_inbound_drop() {
     // but that doesn't change how the state machine behaves.
     let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
 
-    let (connection, client_tx, mut inbound_service, mut peer_outbound_messages, shared_error_slot) =
-        new_test_connection();
+    let (
+        connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
 
     let connection = connection.run(peer_inbound_rx);
 
@@ -223,6 +303,10 @@ async fn connection_run_loop_inbound_drop() {
 
     assert!(client_tx.is_closed());
 
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -244,6 +328,7 @@ async fn connection_run_loop_failed() {
         mut inbound_service,
         mut peer_outbound_messages,
         shared_error_slot,
+        mut event_rx,
     ) = new_test_connection();
 
     // Simulate an internal connection error.
@@ -268,6 +353,142 @@ async fn connection_run_loop_failed() {
     assert!(client_tx.is_closed());
     assert!(peer_inbound_tx.is_closed());
 
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
+    // We need to drop the future, because it holds a mutable reference to the bytes.
+    std::mem::drop(connection_guard);
+    assert!(peer_outbound_messages.next().await.is_none());
+
+    inbound_service.expect_no_requests().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn connection_run_loop_idle_timeout() {
+    zebra_test::init();
+
+    // The real stream and sink are from a split TCP connection,
+    // but that doesn't change how the state machine behaves.
+    let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
+
+    let (
+        mut connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
+
+    connection.idle_timeout = Some(Duration::from_secs(60));
+
+    let connection = connection.run(peer_inbound_rx);
+
+    // If we drop the future, the connection will close anyway, so we avoid the drop by cloning it.
+    let connection = connection.shared();
+    let connection_guard = connection.clone();
+
+    // Start the run loop, so it arms its idle timer, then let the idle timeout elapse.
+    assert_eq!(connection_guard.clone().now_or_never(), None);
+    tokio::time::advance(Duration::from_secs(61)).await;
+
+    let result = connection.now_or_never();
+    assert_eq!(result, Some(()));
+
+    let error = shared_error_slot.try_get_error();
+    assert!(
+        matches!(error.as_ref().map(|error| error.inner_debug()), Some(ref debug) if debug.contains("IdleTimeout")),
+        "expected an idle timeout error, got: {:?}",
+        error
+    );
+
+    assert!(client_tx.is_closed());
+    assert!(peer_inbound_tx.is_closed());
+
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
+    // We need to drop the future, because it holds a mutable reference to the bytes.
+    std::mem::drop(connection_guard);
+    assert!(peer_outbound_messages.next().await.is_none());
+
+    inbound_service.expect_no_requests().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn connection_run_loop_request_timeout() {
+    zebra_test::init();
+
+    // The real stream and sink are from a split TCP connection,
+    // but that doesn't change how the state machine behaves.
+    let (peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
+
+    let (
+        connection,
+        mut client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
+
+    let (response_tx, response_rx) = oneshot::channel();
+    client_tx
+        .try_send(ClientRequest {
+            request: Request::Peers,
+            tx: response_tx.into(),
+            span: tracing::Span::current(),
+            deadline: None,
+        })
+        .expect("client channel should have room for one request");
+
+    let connection = connection.run(peer_inbound_rx);
+    let connection = connection.shared();
+    let connection_guard = connection.clone();
+
+    // Let the run loop pick up the request and install its timer, then let the default
+    // per-request timeout elapse.
+    assert_eq!(connection_guard.clone().now_or_never(), None);
+    tokio::time::advance(DEFAULT_REQUEST_TIMEOUT + Duration::from_secs(1)).await;
+
+    // A request timeout only resolves the one request: the connection itself keeps running.
+    let result = connection.now_or_never();
+    assert_eq!(result, None);
+
+    let error = shared_error_slot.try_get_error();
+    assert!(error.is_none(), "unexpected connection error: {:?}", error);
+
+    let response = response_rx
+        .await
+        .expect("response sender shouldn't be dropped without sending a response");
+    assert!(
+        matches!(
+            response.as_ref().map_err(|error| error.inner_debug()),
+            Err(ref debug) if debug.contains("ClientRequestTimeout")
+        ),
+        "expected a client request timeout error, got: {:?}",
+        response
+    );
+
+    assert!(!client_tx.is_closed());
+    assert!(!peer_inbound_tx.is_closed());
+
+    // The connection went `AwaitingRequest` -> `AwaitingResponse` -> `AwaitingRequest` again,
+    // resolving the timed-out request along the way, but never closed.
+    assert_eq!(
+        drain_events(&mut event_rx),
+        vec![
+            "handshake_complete",
+            "state_changed",
+            "request_received",
+            "state_changed",
+            "response_sent",
+            "state_changed",
+        ]
+    );
+
     // We need to drop the future, because it holds a mutable reference to the bytes.
     std::mem::drop(connection_guard);
     assert!(peer_outbound_messages.next().await.is_none());
@@ -275,6 +496,57 @@ async fn connection_run_loop_failed() {
     inbound_service.expect_no_requests().await;
 }
 
+#[tokio::test]
+async fn connection_run_loop_stale_generation_does_not_clobber_error_slot() {
+    zebra_test::init();
+
+    // The real stream and sink are from a split TCP connection,
+    // but that doesn't change how the state machine behaves.
+    let (_peer_inbound_tx, peer_inbound_rx) = mpsc::channel(1);
+
+    let (
+        mut connection,
+        client_tx,
+        mut inbound_service,
+        mut peer_outbound_messages,
+        shared_error_slot,
+        mut event_rx,
+    ) = new_test_connection();
+
+    // Simulate a reconnection having already happened elsewhere: the shared generation counter
+    // moves on, but this `connection` is still the old one, captured at generation 0.
+    let current_generation = connection.current_generation.clone();
+    current_generation.advance();
+
+    // Drop the client channel, which would normally make the run loop fail and record the
+    // reason. Since this connection has been superseded, it must leave the error slot alone.
+    std::mem::drop(client_tx);
+
+    let connection = connection.run(peer_inbound_rx);
+    let connection = connection.shared();
+    let connection_guard = connection.clone();
+    let result = connection.now_or_never();
+    assert_eq!(result, Some(()));
+
+    let error = shared_error_slot.try_get_error();
+    assert!(
+        error.is_none(),
+        "a stale connection generation must not record an error: {:?}",
+        error
+    );
+
+    // A superseded connection still reports its own lifecycle, it just avoids touching shared
+    // state: `Closed` is emitted locally even though the error slot itself is left alone.
+    let events = drain_events(&mut event_rx);
+    assert_eq!(events.first(), Some(&"handshake_complete"));
+    assert_eq!(events.last(), Some(&"closed"));
+
+    std::mem::drop(connection_guard);
+    assert!(peer_outbound_messages.next().await.is_none());
+
+    inbound_service.expect_no_requests().await;
+}
+
 /// Creates a new [`Connection`] instance for testing.
 fn new_test_connection() -> (
     Connection<MockService<Request, Response, PanicAssertion>, FramedWrite<DuplexStream, Codec>>,
@@ -282,9 +554,11 @@ fn new_test_connection() -> (
     MockService<Request, Response, PanicAssertion>,
     FramedRead<DuplexStream, Codec>,
     ErrorSlot,
+    mpsc::Receiver<PeerConnectionEvent>,
 ) {
     let (client_tx, client_rx) = mpsc::channel(1);
     let (peer_outbound_writer, peer_outbound_reader) = duplex(4096);
+    let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
 
     let codec = Codec::builder()
         .for_network(Network::Mainnet)
@@ -303,11 +577,16 @@ fn new_test_connection() -> (
         cached_addrs: Vec::new(),
         svc: mock_inbound_service.clone(),
         client_rx: ClientRequestReceiver::from(client_rx),
+        in_progress_request: None,
         error_slot: shared_error_slot.clone(),
         peer_tx: peer_outbound_tx,
         connection_tracker: ActiveConnectionCounter::new_counter().track_connection(),
         metrics_label: "test".to_string(),
         last_metrics_state: None,
+        idle_timeout: None,
+        generation: 0,
+        current_generation: ConnectionGeneration::default(),
+        event_tx: Some(event_tx),
     };
 
     (
@@ -316,5 +595,6 @@ fn new_test_connection() -> (
         mock_inbound_service,
         peer_outbound_rx,
         shared_error_slot,
+        event_rx,
     )
 }
@@ -2,6 +2,7 @@ use std::{
     future::Future,
     net::SocketAddr,
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -14,26 +15,46 @@ use zebra_chain::best_tip_height::BestTipHeight;
 
 use crate::{BoxError, Request, Response};
 
-use super::{Client, ConnectedAddr, Handshake};
+use super::{BanTable, Client, ConnectedAddr, Handshake};
 
 /// A wrapper around [`peer::Handshake`] that opens a TCP connection before
 /// forwarding to the inner handshake service. Writing this as its own
 /// [`tower::Service`] lets us apply unified timeout policies, etc.
 pub struct Connector<S, B> {
     handshaker: Handshake<S, B>,
+
+    /// Misbehavior scores and temporary bans, shared with every clone of this `Connector`, so a
+    /// ban recorded by one caller is honored by dial attempts made through any other.
+    bans: Arc<Mutex<BanTable>>,
 }
 
 impl<S: Clone, B: Clone> Clone for Connector<S, B> {
     fn clone(&self) -> Self {
         Connector {
             handshaker: self.handshaker.clone(),
+            bans: self.bans.clone(),
         }
     }
 }
 
 impl<S, B> Connector<S, B> {
     pub fn new(handshaker: Handshake<S, B>) -> Self {
-        Connector { handshaker }
+        Connector {
+            handshaker,
+            bans: Arc::new(Mutex::new(BanTable::new())),
+        }
+    }
+
+    /// Returns the shared [`BanTable`] this `Connector` consults before dialing, so callers that
+    /// observe misbehavior elsewhere (for example, in [`Connection`][super::Connection]'s run
+    /// loop) can record it.
+    ///
+    /// TODO: have the run loop call this automatically when it closes a connection with a
+    /// scoreable `PeerError`. That needs a `BanTable` handle threaded into `Connection` at
+    /// construction time, which happens inside `Handshake::call` - currently a stub that never
+    /// actually builds a `Connection` (see the TODO there), so there's nowhere to plumb it yet.
+    pub fn bans(&self) -> Arc<Mutex<BanTable>> {
+        self.bans.clone()
     }
 }
 
@@ -52,7 +73,21 @@ where
         Poll::Ready(Ok(()))
     }
 
+    // TODO: feed the connection outcome back into `peer_set::AddressBook` via
+    //       `record_success`/`record_failure`, so eclipse-resistant dial candidate selection can
+    //       take recent connection history into account. That needs a handle to the
+    //       `CandidateSet`'s `AddressBook` threaded in here, which in turn needs `CandidateSet`
+    //       itself - currently absent as a file in this tree, with only its test module left.
     fn call(&mut self, addr: SocketAddr) -> Self::Future {
+        if self
+            .bans
+            .lock()
+            .expect("bans mutex should be unpoisoned")
+            .is_banned(&addr)
+        {
+            return async move { Err("peer address is temporarily banned".into()) }.boxed();
+        }
+
         let mut hs = self.handshaker.clone();
         let connected_addr = ConnectedAddr::new_outbound_direct(addr);
         let connector_span = info_span!("connector", peer = ?connected_addr);
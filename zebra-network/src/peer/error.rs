@@ -0,0 +1,158 @@
+//! Error types for peer connections.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::BoxError;
+
+/// A wrapper around `Arc<PeerError>` that implements `Error`.
+#[derive(Error, Debug, Clone)]
+#[error(transparent)]
+pub struct SharedPeerError(Arc<PeerError>);
+
+impl<E> From<E> for SharedPeerError
+where
+    PeerError: From<E>,
+{
+    fn from(source: E) -> Self {
+        Self(Arc::new(PeerError::from(source)))
+    }
+}
+
+impl SharedPeerError {
+    /// Returns the `Debug` representation of the inner [`PeerError`], so tests can check which
+    /// kind of error this is.
+    #[allow(dead_code)]
+    pub fn inner_debug(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// An error related to peer connection handling.
+#[derive(Error, Debug)]
+#[allow(dead_code)]
+pub enum PeerError {
+    /// The remote peer closed the connection.
+    #[error("the remote peer closed the connection")]
+    ConnectionClosed,
+
+    /// The local node dropped the connection.
+    #[error("the local node dropped the connection")]
+    ConnectionDropped,
+
+    /// A panic occurred in a spawned connection task.
+    #[error("a panic occurred in a peer connection task")]
+    ConnectionTaskPanicked,
+
+    /// The connection's heartbeat task exited, so the connection can no longer make progress.
+    #[error("the heartbeat task exited")]
+    HeartbeatTaskExited,
+
+    /// A panic occurred in a spawned heartbeat task.
+    #[error("a panic occurred in a peer heartbeat task")]
+    HeartbeatTaskPanicked,
+
+    /// The client was dropped while the request was still in flight.
+    #[error("client was dropped while the request was still in flight")]
+    ClientDropped,
+
+    /// A request timed out waiting for the peer to respond.
+    #[error("client request timed out")]
+    ClientRequestTimeout,
+
+    /// A connection had no activity for longer than the configured idle timeout.
+    #[error("connection idle for longer than the configured timeout")]
+    IdleTimeout,
+
+    /// The connection was gracefully shut down.
+    #[error("the connection was gracefully shut down")]
+    Shutdown,
+
+    /// A handshake with this peer was already in progress.
+    #[error("duplicate handshake detected")]
+    DuplicateHandshake,
+
+    /// The peer set was overloaded and rejected this connection.
+    #[error("peer connection was dropped because the peer set was overloaded")]
+    Overloaded,
+
+    /// Wraps lower-level errors, for example from `tokio::io`.
+    #[error(transparent)]
+    Other(#[from] BoxError),
+}
+
+/// An error during a peer handshake.
+#[derive(Error, Debug)]
+#[allow(dead_code)]
+pub enum HandshakeError {
+    /// The remote peer closed the connection before completing the handshake.
+    #[error("the remote peer closed the connection before completing the handshake")]
+    ConnectionClosed,
+
+    /// The handshake timed out.
+    #[error("handshake timed out")]
+    Timeout,
+
+    /// The remote peer sent a message that was not expected during a handshake.
+    #[error("remote peer sent an unexpected message during handshake")]
+    UnexpectedMessage,
+
+    /// The remote peer advertises a protocol version that is too old to be usable.
+    #[error("remote peer's protocol version is too old: {0:?}")]
+    ObsoleteVersion(crate::protocol::external::types::Version),
+
+    /// Wraps lower-level errors, for example from `tokio::io`.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// A shared slot for an error that closed a connection.
+///
+/// Multiple tasks can clone an `ErrorSlot`, and each one can try to set the connection error.
+/// Only the first error is retained; later updates are rejected.
+#[derive(Default, Clone)]
+pub struct ErrorSlot(Arc<Mutex<Option<SharedPeerError>>>);
+
+impl ErrorSlot {
+    /// Read the current error in the slot, without updating it.
+    pub fn try_get_error(&self) -> Option<SharedPeerError> {
+        self.0
+            .lock()
+            .expect("error mutex should be unpoisoned")
+            .clone()
+    }
+
+    /// Update the slot with `error`, if it does not already contain an error.
+    ///
+    /// Returns `Ok(())` if the slot was empty, or `Err` with the existing error if it was already
+    /// set.
+    pub fn try_update_error(&self, error: SharedPeerError) -> Result<(), SharedPeerError> {
+        let mut guard = self.0.lock().expect("error mutex should be unpoisoned");
+
+        match &*guard {
+            Some(existing) => Err(existing.clone()),
+            None => {
+                *guard = Some(error);
+                Ok(())
+            }
+        }
+    }
+
+    /// Clear any error in the slot, so that it can accept a new one.
+    ///
+    /// Used when a [`Client`][crate::peer::Client] reconnects after a connection failure: the
+    /// slot is reused for the new connection, rather than allocating a fresh one, so it has to
+    /// be emptied out first.
+    pub(crate) fn reset(&self) {
+        *self.0.lock().expect("error mutex should be unpoisoned") = None;
+    }
+}
+
+/// The default duration that [`Client::shutdown`] waits for outstanding work to complete, before
+/// giving up and forcing the connection closed.
+#[allow(dead_code)]
+pub(crate) const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
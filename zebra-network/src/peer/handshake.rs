@@ -0,0 +1,125 @@
+//! Initial [`Handshake`]s with peers over a TCP connection.
+
+use std::{
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::prelude::*;
+use tokio::net::TcpStream;
+use tower::Service;
+
+use crate::{peer::HandshakeError, BoxError, Request, Response};
+
+/// The address of a peer, annotated with how we became aware of it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ConnectedAddr {
+    /// A connection that we initiated, by connecting to the given address.
+    OutboundDirect {
+        /// The address we connected to.
+        addr: SocketAddr,
+    },
+
+    /// A connection that a peer initiated, from the given address.
+    Inbound {
+        /// The address the peer connected from.
+        addr: SocketAddr,
+    },
+}
+
+impl ConnectedAddr {
+    /// Create a [`ConnectedAddr`] for an outbound connection to `addr`.
+    pub fn new_outbound_direct(addr: SocketAddr) -> Self {
+        ConnectedAddr::OutboundDirect { addr }
+    }
+
+    /// Create a [`ConnectedAddr`] for an inbound connection from `addr`.
+    pub fn new_inbound(addr: SocketAddr) -> Self {
+        ConnectedAddr::Inbound { addr }
+    }
+
+    /// Returns the peer's address, regardless of connection direction.
+    pub fn addr(&self) -> SocketAddr {
+        match self {
+            ConnectedAddr::OutboundDirect { addr } | ConnectedAddr::Inbound { addr } => *addr,
+        }
+    }
+}
+
+impl fmt::Display for ConnectedAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectedAddr::OutboundDirect { addr } => write!(f, "Outbound({})", addr),
+            ConnectedAddr::Inbound { addr } => write!(f, "Inbound({})", addr),
+        }
+    }
+}
+
+/// A request to a [`Handshake`] service, consisting of an established TCP stream and the
+/// [`ConnectedAddr`] it was accepted or opened from.
+pub type HandshakeRequest = (TcpStream, ConnectedAddr);
+
+/// A [`tower::Service`] that negotiates a Zcash network protocol handshake over an established
+/// TCP connection, and produces a [`Client`][super::Client] for the resulting peer connection.
+pub struct Handshake<S, B> {
+    inbound_service: S,
+    best_tip_height: B,
+}
+
+impl<S: Clone, B: Clone> Clone for Handshake<S, B> {
+    fn clone(&self) -> Self {
+        Handshake {
+            inbound_service: self.inbound_service.clone(),
+            best_tip_height: self.best_tip_height.clone(),
+        }
+    }
+}
+
+impl<S, B> Handshake<S, B> {
+    /// Create a new [`Handshake`] service, using `inbound_service` to answer requests from
+    /// peers, and `best_tip_height` to report our current chain tip height during the handshake.
+    pub fn new(inbound_service: S, best_tip_height: B) -> Self {
+        Handshake {
+            inbound_service,
+            best_tip_height,
+        }
+    }
+}
+
+impl<S, B> Service<HandshakeRequest> for Handshake<S, B>
+where
+    S: Service<Request, Response = Response, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: Clone + Send + 'static,
+{
+    type Response = super::Client;
+    type Error = BoxError;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, (stream, connected_addr): HandshakeRequest) -> Self::Future {
+        let mut inbound_service = self.inbound_service.clone();
+        let best_tip_height = self.best_tip_height.clone();
+
+        async move {
+            // TODO: perform the `version`/`verack` exchange over `stream` and build the
+            //       `Connection` actor that backs the returned `Client` (#1165).
+            //
+            //       Once that exchange exists, parse the peer's advertised `PeerServices` out of
+            //       the `version` message's services field and pass them to `Client` alongside
+            //       its negotiated `Version`, the same way `version` itself is threaded through
+            //       today - this crate's wire `Message` type doesn't have a `Version` variant to
+            //       read that field from yet.
+            let _ = (&mut inbound_service, &best_tip_height, &stream, connected_addr);
+
+            Err(HandshakeError::ConnectionClosed.into())
+        }
+        .boxed()
+    }
+}
@@ -1,7 +1,41 @@
 use std::net::SocketAddr;
 
+use bitflags::bitflags;
+
 use crate::protocol::external::types::Version;
 
+bitflags! {
+    /// The services advertised by a peer, as carried in the 64-bit services field of its
+    /// `version` message.
+    ///
+    /// Bits that we don't recognise are preserved rather than rejected, since a peer running
+    /// newer software than us may legitimately advertise service flags we don't know about yet.
+    pub struct PeerServices: u64 {
+        /// This peer can be asked for full blocks, rather than just headers.
+        const NODE_NETWORK = 1;
+        /// This peer supports bloom filters over the transactions it relays (BIP 111).
+        const NODE_BLOOM = 1 << 2;
+    }
+}
+
+#[cfg(any(test, feature = "proptest-impl"))]
+impl proptest::arbitrary::Arbitrary for PeerServices {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        // Generate arbitrary bit patterns, not just combinations of the flags we know about, so
+        // proptests also cover peers advertising services we don't recognise. `from_bits_retain`
+        // keeps those unrecognised bits rather than silently discarding them - `from_bits_truncate`
+        // would defeat the point of this proptest before it even generated a value.
+        any::<u64>()
+            .prop_map(PeerServices::from_bits_retain)
+            .boxed()
+    }
+}
+
 /// Meta-data extracted from a peer connection.
 #[derive(Clone, Copy, Debug)]
 pub struct PeerMetaData {
@@ -10,12 +44,19 @@ pub struct PeerMetaData {
 
     /// The peer's reported protocol version.
     version: Version,
+
+    /// The services the peer advertised in its `version` message.
+    services: PeerServices,
 }
 
 impl PeerMetaData {
     /// Create a new [`PeerMetaData`] with the provided meta-data.
-    pub fn new(address: SocketAddr, version: Version) -> Self {
-        PeerMetaData { address, version }
+    pub fn new(address: SocketAddr, version: Version, services: PeerServices) -> Self {
+        PeerMetaData {
+            address,
+            version,
+            services,
+        }
     }
 
     /// Retrieve the peer's address.
@@ -27,6 +68,11 @@ impl PeerMetaData {
     pub fn version(&self) -> Version {
         self.version
     }
+
+    /// Retrieve the services the peer advertised.
+    pub fn services(&self) -> PeerServices {
+        self.services
+    }
 }
 
 /// [`PartialEq`] and [`Eq`] can be used to see if two [`PeerMetaData`]s refer to the same peer.
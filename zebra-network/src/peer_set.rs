@@ -1,4 +1,8 @@
+mod addr_manager;
+mod address_book;
 pub(crate) mod candidate_set;
+mod clock_skew;
+mod feeler;
 mod initialize;
 mod inventory_registry;
 mod limit;
@@ -7,6 +11,7 @@ mod set;
 mod signals;
 mod unready_service;
 
+pub(crate) use address_book::AddressBook;
 pub(crate) use candidate_set::CandidateSet;
 pub(crate) use limit::{ActiveConnectionCounter, ConnectionTracker};
 
@@ -0,0 +1,492 @@
+//! A Bitcoin addrman-style bucketed address manager, for resisting eclipse attacks from a source
+//! that floods us with addresses from a single netgroup.
+//!
+//! Addresses move through two tables:
+//! - *new*: addresses we've learned about from `addr` gossip, but never successfully connected
+//!   to, spread across [`NEW_BUCKETS`] buckets.
+//! - *tried*: addresses we've successfully connected to at least once, spread across
+//!   [`TRIED_BUCKETS`] buckets.
+//!
+//! Unlike [`super::address_book::AddressBook`], which caps each netgroup to a fixed number of
+//! entries across the whole tier, this manager places every address into a *bucket* chosen
+//! deterministically from a per-manager secret key, the gossiping source's netgroup, and the
+//! address's own netgroup (for "new"), or just the address's netgroup (for "tried"). Each bucket
+//! has a small, bounded number of slots, and each address hashes to a single slot in its bucket;
+//! if that slot is already taken, a new address can only displace the occupant if that occupant
+//! is judged [`terrible`](Entry::is_terrible).
+//! Since the bucket (and slot) an address lands in don't depend on how many *other* addresses an
+//! attacker has gossiped, flooding us with addresses from one netgroup can win at most a handful
+//! of buckets, rather than crowding out the rest of the table.
+//!
+//! TODO: `candidate_set.rs` is absent from this checkout (only its test modules survive), so there
+//! is no `CandidateSet::update` to call [`AddrManager::record_gossiped`]/[`AddrManager::select`]
+//! from yet. This module is written standalone, ready to be wired in as `CandidateSet`'s backing
+//! store once that file exists.
+//!
+//! [`super::feeler`] uses [`AddrManager::select_new`] to pick "new" addresses to probe, and
+//! records each probe's outcome here. [`AddrManager::select`] still returns "new" addresses
+//! whether or not they've been probed.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    net::SocketAddr,
+};
+
+use chrono::{DateTime, Utc};
+use rand::{thread_rng, Rng};
+
+use super::address_book::{Clock, NetGroup, SystemClock};
+
+/// The number of buckets in the "new" table.
+pub const NEW_BUCKETS: usize = 1024;
+
+/// The number of buckets in the "tried" table.
+pub const TRIED_BUCKETS: usize = 256;
+
+/// The number of slots in each bucket, in either table.
+pub const SLOTS_PER_BUCKET: usize = 64;
+
+/// An address's age, in failed attempts since its last success, beyond which it's considered
+/// terrible regardless of how recently it was last tried.
+const MAX_FAILED_ATTEMPTS: u32 = 10;
+
+/// How long an address can go without a successful connection before it's considered terrible,
+/// even if it hasn't racked up [`MAX_FAILED_ATTEMPTS`] failures.
+fn max_age() -> chrono::Duration {
+    chrono::Duration::days(30)
+}
+
+/// An address tracked in the "new" or "tried" table.
+#[derive(Clone, Debug)]
+struct Entry {
+    /// The peer that gossiped this address to us, used to compute its "new"-table bucket.
+    ///
+    /// Kept even after promotion to "tried", so that a demotion back to "new" (see
+    /// [`AddrManager::record_success`]) has a source to bucket by again.
+    source: SocketAddr,
+
+    /// The last time we successfully connected to this address, if ever.
+    last_response: Option<DateTime<Utc>>,
+
+    /// The last time we attempted to connect to this address, if ever.
+    last_attempt: Option<DateTime<Utc>>,
+
+    /// The last time a connection attempt to this address failed, if ever.
+    last_failure: Option<DateTime<Utc>>,
+
+    /// The number of connection attempts since the last success (or ever, if there's never been
+    /// one).
+    failed_attempts: u32,
+}
+
+impl Entry {
+    fn fresh(source: SocketAddr) -> Self {
+        Entry {
+            source,
+            last_response: None,
+            last_attempt: None,
+            last_failure: None,
+            failed_attempts: 0,
+        }
+    }
+
+    /// Returns `true` if this entry is a poor use of a bucket slot: it has failed too many times
+    /// in a row, or it's stale enough that it's unlikely to still be reachable.
+    fn is_terrible(&self, now: DateTime<Utc>) -> bool {
+        if self.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            return true;
+        }
+
+        match self.last_response {
+            Some(last_response) => now - last_response > max_age(),
+            // Never succeeded, and tried at least once without success recently enough to not
+            // already be caught by `MAX_FAILED_ATTEMPTS`: not terrible yet, just unproven.
+            None => false,
+        }
+    }
+}
+
+/// A Bitcoin addrman-style bucketed address manager.
+///
+/// Generic over its time source `C`, following the same pluggable-[`Clock`] convention as
+/// [`super::address_book::AddressBook`], so tests can script a timeline deterministically.
+#[derive(Debug)]
+pub struct AddrManager<C: Clock = SystemClock> {
+    /// A per-manager secret, mixed into every bucket/slot hash so an external attacker can't
+    /// predict (and therefore deliberately target) which bucket an address they control will
+    /// land in.
+    key: u64,
+
+    new_table: HashMap<SocketAddr, Entry>,
+    tried_table: HashMap<SocketAddr, Entry>,
+
+    clock: C,
+}
+
+impl Default for AddrManager<SystemClock> {
+    fn default() -> Self {
+        AddrManager::with_clock(SystemClock)
+    }
+}
+
+impl AddrManager<SystemClock> {
+    /// Create a new, empty [`AddrManager`] backed by the real wall-clock time, with a fresh
+    /// random secret key.
+    pub fn new() -> Self {
+        AddrManager::default()
+    }
+}
+
+impl<C: Clock> AddrManager<C> {
+    /// Create a new, empty [`AddrManager`] using `clock` as its time source.
+    ///
+    /// Production code should use [`AddrManager::new`]; this is for tests that need to script a
+    /// timeline with a fake [`Clock`].
+    pub fn with_clock(clock: C) -> Self {
+        AddrManager {
+            key: thread_rng().gen(),
+            new_table: HashMap::new(),
+            tried_table: HashMap::new(),
+            clock,
+        }
+    }
+
+    /// Record `address`, as gossiped to us by `source`, in the "new" table.
+    ///
+    /// Does nothing if `address` is already tracked in either table. If the address's target
+    /// bucket slot is occupied by another "new" entry, the occupant is evicted only if it's
+    /// [`terrible`](Entry::is_terrible); otherwise the incumbent is kept and `address` is
+    /// dropped, exactly as an attacker flooding a single bucket should be dropped.
+    pub fn record_gossiped(&mut self, address: SocketAddr, source: SocketAddr) {
+        if self.tried_table.contains_key(&address) || self.new_table.contains_key(&address) {
+            return;
+        }
+
+        let (bucket, slot) = self.new_bucket_slot(&address, &source);
+
+        if let Some(occupant) = self.new_occupant(bucket, slot) {
+            if self.new_table[&occupant].is_terrible(self.clock.now()) {
+                self.new_table.remove(&occupant);
+            } else {
+                return;
+            }
+        }
+
+        self.new_table.insert(address, Entry::fresh(source));
+    }
+
+    /// Record a successful connection to `address`, promoting it from "new" to "tried".
+    ///
+    /// If `address`'s "tried" bucket slot is already occupied, the existing occupant is demoted
+    /// back into the "new" table (rebucketed by its own recorded source), rather than being
+    /// dropped outright - a demotion, not an eviction.
+    pub fn record_success(&mut self, address: SocketAddr) {
+        let now = self.clock.now();
+
+        let mut entry = self
+            .new_table
+            .remove(&address)
+            .or_else(|| self.tried_table.remove(&address))
+            .unwrap_or_else(|| Entry::fresh(address));
+
+        entry.last_response = Some(now);
+        entry.last_attempt = Some(now);
+        entry.failed_attempts = 0;
+
+        let (bucket, slot) = self.tried_bucket_slot(&address);
+
+        if let Some(occupant) = self.tried_occupant(bucket, slot) {
+            if let Some(demoted) = self.tried_table.remove(&occupant) {
+                self.new_table.insert(occupant, demoted);
+            }
+        }
+
+        self.tried_table.insert(address, entry);
+    }
+
+    /// Record a failed connection attempt to `address`, wherever it's currently tracked.
+    pub fn record_failure(&mut self, address: SocketAddr) {
+        let now = self.clock.now();
+
+        for table in [&mut self.new_table, &mut self.tried_table] {
+            if let Some(entry) = table.get_mut(&address) {
+                entry.last_attempt = Some(now);
+                entry.last_failure = Some(now);
+                entry.failed_attempts += 1;
+                return;
+            }
+        }
+    }
+
+    /// Selects a "new"-table address to probe with a [`super::feeler`] connection, or `None` if
+    /// it's empty.
+    ///
+    /// Unlike [`Self::select`], this never returns a "tried" address: feeler connections exist to
+    /// find out whether an as-yet-unproven address is reachable at all, not to re-check one we've
+    /// already connected to successfully.
+    pub fn select_new(&self) -> Option<SocketAddr> {
+        Self::random_key(&self.new_table)
+    }
+
+    /// Returns `true` if `address`'s most recent connection attempt - whether a real dial or a
+    /// [`super::feeler`] probe - is a recorded failure more recent than its last success.
+    ///
+    /// Intended for `Request::Peers` sanitization, so an address that's failed its most recent
+    /// feeler probe isn't handed out to other peers as if it were still a good candidate.
+    pub fn is_known_unreachable(&self, address: &SocketAddr) -> bool {
+        let entry = match self
+            .new_table
+            .get(address)
+            .or_else(|| self.tried_table.get(address))
+        {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        match (entry.last_failure, entry.last_response) {
+            (Some(last_failure), Some(last_response)) => last_failure > last_response,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Selects a candidate address to dial, biased towards the "tried" table.
+    ///
+    /// Returns `None` if both tables are empty.
+    pub fn select(&self) -> Option<SocketAddr> {
+        /// The chance, out of 100, that a non-empty "tried" table is preferred over "new".
+        ///
+        /// Mirrors Bitcoin Core's addrman bias toward addresses that have already proven
+        /// reachable, while still giving "new" entries a real chance to be tried.
+        const TRIED_BIAS_PERCENT: u32 = 70;
+
+        let prefer_tried = thread_rng().gen_ratio(TRIED_BIAS_PERCENT, 100);
+
+        let primary = if prefer_tried {
+            &self.tried_table
+        } else {
+            &self.new_table
+        };
+        let fallback = if prefer_tried {
+            &self.new_table
+        } else {
+            &self.tried_table
+        };
+
+        Self::random_key(primary).or_else(|| Self::random_key(fallback))
+    }
+
+    /// Returns a uniformly random key from `table`, or `None` if it's empty.
+    fn random_key(table: &HashMap<SocketAddr, Entry>) -> Option<SocketAddr> {
+        let index = thread_rng().gen_range(0..table.len().max(1));
+        table.keys().nth(index).copied()
+    }
+
+    /// Returns the "new"-table address, if any, that currently occupies `(bucket, slot)`.
+    fn new_occupant(&self, bucket: usize, slot: usize) -> Option<SocketAddr> {
+        self.new_table
+            .iter()
+            .find(|(candidate, entry)| self.new_bucket_slot(candidate, &entry.source) == (bucket, slot))
+            .map(|(address, _)| *address)
+    }
+
+    /// Returns the "tried"-table address, if any, that currently occupies `(bucket, slot)`.
+    fn tried_occupant(&self, bucket: usize, slot: usize) -> Option<SocketAddr> {
+        self.tried_table
+            .iter()
+            .find(|(candidate, _)| self.tried_bucket_slot(candidate) == (bucket, slot))
+            .map(|(address, _)| *address)
+    }
+
+    /// Returns the `(bucket, slot)` an `address` gossiped by `source` is placed at in the "new"
+    /// table: `bucket = H(key, src_group, addr_group) % NEW_BUCKETS`.
+    fn new_bucket_slot(&self, address: &SocketAddr, source: &SocketAddr) -> (usize, usize) {
+        let src_group = NetGroup::for_addr(source);
+        let addr_group = NetGroup::for_addr(address);
+
+        let bucket = (Self::hash(self.key, &[b"new", &src_group.0, &addr_group.0]) as usize)
+            % NEW_BUCKETS;
+        let slot = (Self::hash(self.key, &[b"new-slot", &bucket.to_le_bytes(), &address_bytes(address)])
+            as usize)
+            % SLOTS_PER_BUCKET;
+
+        (bucket, slot)
+    }
+
+    /// Returns the `(bucket, slot)` an `address` is placed at in the "tried" table:
+    /// `bucket = H(key, addr_group) % TRIED_BUCKETS`.
+    fn tried_bucket_slot(&self, address: &SocketAddr) -> (usize, usize) {
+        let addr_group = NetGroup::for_addr(address);
+
+        let bucket =
+            (Self::hash(self.key, &[b"tried", &addr_group.0]) as usize) % TRIED_BUCKETS;
+        let slot = (Self::hash(
+            self.key,
+            &[b"tried-slot", &bucket.to_le_bytes(), &address_bytes(address)],
+        ) as usize)
+            % SLOTS_PER_BUCKET;
+
+        (bucket, slot)
+    }
+
+    /// Hashes `key` together with every byte slice in `parts`, in order.
+    fn hash(key: u64, parts: &[&[u8]]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        for part in parts {
+            part.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// Returns a stable byte representation of `address`, for mixing into a bucket/slot hash.
+fn address_bytes(address: &SocketAddr) -> Vec<u8> {
+    match address {
+        SocketAddr::V4(address) => {
+            let mut bytes = address.ip().octets().to_vec();
+            bytes.extend_from_slice(&address.port().to_le_bytes());
+            bytes
+        }
+        SocketAddr::V6(address) => {
+            let mut bytes = address.ip().octets().to_vec();
+            bytes.extend_from_slice(&address.port().to_le_bytes());
+            bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::{super::address_book::SystemClock, AddrManager};
+
+    fn addr(octets: [u8; 4], port: u16) -> SocketAddr {
+        SocketAddr::from((octets, port))
+    }
+
+    #[test]
+    fn gossiped_address_is_selectable_and_not_yet_tried() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+        let address = addr([203, 0, 113, 5], 8233);
+
+        manager.record_gossiped(address, source);
+
+        assert_eq!(manager.select(), Some(address));
+        assert!(manager.tried_table.is_empty());
+    }
+
+    #[test]
+    fn successful_connection_promotes_address_to_tried() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+        let address = addr([203, 0, 113, 5], 8233);
+
+        manager.record_gossiped(address, source);
+        manager.record_success(address);
+
+        assert!(manager.new_table.is_empty());
+        assert!(manager.tried_table.contains_key(&address));
+    }
+
+    #[test]
+    fn bucket_collision_keeps_the_incumbent_when_it_is_not_terrible() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+
+        // Find two addresses that land in the same new-table bucket and slot, by brute force -
+        // every candidate shares a source and a /16 netgroup, so they all land in the same bucket
+        // and only need to collide on one of its 64 slots.
+        let mut first = None;
+        let mut second = None;
+
+        'search: for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                let candidate = addr([198, 51, a, b], 8233);
+                let target = manager.new_bucket_slot(&candidate, &source);
+
+                match first {
+                    None => first = Some((candidate, target)),
+                    Some((_, first_target)) if first_target == target => {
+                        second = Some(candidate);
+                        break 'search;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let first = first.expect("at least one candidate address").0;
+        let second = second.expect("a colliding address should exist within the search space");
+
+        manager.record_gossiped(first, source);
+        manager.record_gossiped(second, source);
+
+        // Only one of the two colliding addresses should have made it into the table.
+        let tracked = [first, second]
+            .into_iter()
+            .filter(|address| manager.new_table.contains_key(address))
+            .count();
+        assert_eq!(tracked, 1);
+    }
+
+    #[test]
+    fn repeated_failures_make_an_entry_terrible() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+        let address = addr([203, 0, 113, 5], 8233);
+
+        manager.record_gossiped(address, source);
+        for _ in 0..super::MAX_FAILED_ATTEMPTS {
+            manager.record_failure(address);
+        }
+
+        let entry = &manager.new_table[&address];
+        assert!(entry.is_terrible(chrono::Utc::now()));
+    }
+
+    #[test]
+    fn empty_manager_has_no_candidate() {
+        let manager = AddrManager::<SystemClock>::new();
+        assert_eq!(manager.select(), None);
+    }
+
+    #[test]
+    fn select_new_never_returns_a_tried_address() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+        let untried = addr([203, 0, 113, 5], 8233);
+        let tried = addr([203, 0, 113, 6], 8233);
+
+        manager.record_gossiped(untried, source);
+        manager.record_gossiped(tried, source);
+        manager.record_success(tried);
+
+        assert_eq!(manager.select_new(), Some(untried));
+    }
+
+    #[test]
+    fn failed_feeler_probe_marks_address_unreachable() {
+        let mut manager = AddrManager::<SystemClock>::new();
+        let source = addr([10, 0, 0, 1], 8233);
+        let address = addr([203, 0, 113, 5], 8233);
+
+        manager.record_gossiped(address, source);
+        assert!(
+            !manager.is_known_unreachable(&address),
+            "an address that's never been tried isn't known to be unreachable"
+        );
+
+        manager.record_failure(address);
+        assert!(manager.is_known_unreachable(&address));
+
+        manager.record_success(address);
+        assert!(
+            !manager.is_known_unreachable(&address),
+            "a subsequent success should clear the unreachable status"
+        );
+    }
+}
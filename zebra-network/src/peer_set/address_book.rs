@@ -0,0 +1,419 @@
+//! A Monero-style tiered address book, used to pick dial candidates while resisting eclipse
+//! attacks.
+//!
+//! Addresses move through three tiers:
+//! - *gray*: untried addresses learned from `addr` gossip.
+//! - *white*: addresses we've successfully handshaked with, promoted from gray on success and
+//!   demoted back on repeated failure.
+//! - *anchor*: a small fixed set of currently-connected peers, persisted to disk so that on
+//!   restart we reconnect to the same peers first.
+//!
+//! White and gray entries are bucketed by netgroup (an IPv4 /16, or the first two segments of an
+//! IPv6 address) and each netgroup is capped to [`MAX_PER_NETGROUP`] entries, so an attacker who
+//! controls many addresses in the same network can't flood either tier and dominate who we dial.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use rand::{seq::IteratorRandom, thread_rng};
+
+/// The maximum number of addresses any single netgroup may occupy in the white or gray tier.
+const MAX_PER_NETGROUP: usize = 8;
+
+/// The number of consecutive failures after which a white-listed address is demoted back to gray.
+const MAX_CONSECUTIVE_FAILURES: usize = 3;
+
+/// The maximum number of anchor peers persisted across restarts.
+const MAX_ANCHORS: usize = 2;
+
+/// The base reconnect backoff applied after a single failed connection attempt, doubled for each
+/// further consecutive failure.
+const RETRY_BACKOFF_BASE: Duration = Duration::from_secs(10);
+
+/// A source of the current time, injectable so tests can script a timeline instead of depending
+/// on wall-clock `Utc::now()`.
+///
+/// This is the same pluggable-clock shape as `zebra_chain::best_tip_height::BestTipHeight`, which
+/// [`super::super::peer::Connector`] already threads through as a generic parameter - `AddressBook`
+/// follows it here so tests can exercise reconnect backoff deterministically.
+pub trait Clock: Clone {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A [`Clock`] backed by the real wall-clock time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The coarse network bucket an address belongs to, used to cap how many addresses from the same
+/// network can occupy a tier.
+///
+/// `pub(crate)` so [`super::addr_manager`] can reuse the same netgroup definition, rather than
+/// maintaining a second copy of the IPv4 /16 / IPv6 /32 grouping rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct NetGroup(pub(crate) [u8; 4]);
+
+impl NetGroup {
+    /// Returns the netgroup `address` belongs to: an IPv4 /16, or the first two segments of an
+    /// IPv6 address (roughly a /32).
+    pub(crate) fn for_addr(address: &SocketAddr) -> Self {
+        match address.ip() {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                NetGroup([octets[0], octets[1], 0, 0])
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                let first = segments[0].to_be_bytes();
+                let second = segments[1].to_be_bytes();
+                NetGroup([first[0], first[1], second[0], second[1]])
+            }
+        }
+    }
+}
+
+/// An address tracked in the gray or white tier.
+#[derive(Clone, Copy, Debug)]
+struct AddressEntry {
+    address: SocketAddr,
+    consecutive_failures: usize,
+    /// The time before which this address shouldn't be offered as a dial candidate again, set
+    /// after a failed connection attempt. `None` if the address has never failed, or has since
+    /// succeeded.
+    retry_at: Option<DateTime<Utc>>,
+}
+
+/// A tiered address book: gray (untried), white (known good), and anchor (persisted, currently
+/// connected) peers.
+///
+/// Generic over its time source `C` so tests can script a timeline with a fake [`Clock`] instead
+/// of depending on wall-clock time; production code uses the default [`SystemClock`].
+#[derive(Debug)]
+pub struct AddressBook<C: Clock = SystemClock> {
+    gray: HashMap<SocketAddr, AddressEntry>,
+    white: HashMap<SocketAddr, AddressEntry>,
+    anchors: Vec<SocketAddr>,
+    clock: C,
+}
+
+impl Default for AddressBook<SystemClock> {
+    fn default() -> Self {
+        AddressBook::with_clock(SystemClock)
+    }
+}
+
+impl AddressBook<SystemClock> {
+    /// Create a new, empty [`AddressBook`] backed by the real wall-clock time.
+    pub fn new() -> Self {
+        AddressBook::default()
+    }
+}
+
+impl<C: Clock> AddressBook<C> {
+    /// Create a new, empty [`AddressBook`] using `clock` as its time source.
+    ///
+    /// Production code should use [`AddressBook::new`]; this is for tests that need to script a
+    /// timeline with a fake [`Clock`].
+    pub fn with_clock(clock: C) -> Self {
+        AddressBook {
+            gray: HashMap::new(),
+            white: HashMap::new(),
+            anchors: Vec::new(),
+            clock,
+        }
+    }
+
+    /// Record `address` as learned from `addr` gossip, adding it to the gray tier.
+    ///
+    /// Does nothing if `address` is already tracked in gray or white, or if its netgroup is
+    /// already at capacity in gray.
+    pub fn record_gossiped(&mut self, address: SocketAddr) {
+        if self.white.contains_key(&address) || self.gray.contains_key(&address) {
+            return;
+        }
+
+        if self.netgroup_count(&self.gray, &address) >= MAX_PER_NETGROUP {
+            return;
+        }
+
+        self.gray.insert(address, AddressEntry::fresh(address));
+    }
+
+    /// Record a successful handshake with `address`, promoting it to the white tier.
+    ///
+    /// If white is already at capacity for `address`'s netgroup, the existing white entry from
+    /// the same netgroup with the most consecutive failures is evicted to make room.
+    pub fn record_success(&mut self, address: SocketAddr) {
+        self.gray.remove(&address);
+
+        if !self.white.contains_key(&address)
+            && self.netgroup_count(&self.white, &address) >= MAX_PER_NETGROUP
+        {
+            match self.worst_in_netgroup(&address) {
+                Some(victim) => {
+                    self.white.remove(&victim);
+                }
+                // `worst_in_netgroup` only returns `None` when this netgroup has no white
+                // entries, which can't happen while it's at capacity.
+                None => return,
+            }
+        }
+
+        self.white.insert(address, AddressEntry::fresh(address));
+    }
+
+    /// Record a failed connection attempt to `address`.
+    ///
+    /// A white-listed address is demoted back to gray after [`MAX_CONSECUTIVE_FAILURES`]; a
+    /// gray-listed address is evicted outright, since it was never proven to work in the first
+    /// place. A surviving entry is also given a reconnect backoff, doubling with each consecutive
+    /// failure, so [`Self::dial_candidate`] won't immediately retry it.
+    pub fn record_failure(&mut self, address: SocketAddr) {
+        if let Some(entry) = self.white.get_mut(&address) {
+            entry.consecutive_failures += 1;
+
+            if entry.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                self.white.remove(&address);
+
+                // Demote back to gray rather than forgetting the address outright, unless its
+                // netgroup is already full there - the same policy [`Self::record_gossiped`]
+                // uses, which never evicts an existing gray entry to make room for a new one.
+                if self.netgroup_count(&self.gray, &address) < MAX_PER_NETGROUP {
+                    self.gray.insert(address, AddressEntry::fresh(address));
+                }
+
+                return;
+            }
+
+            entry.retry_at = Some(self.clock.now() + Self::backoff(entry.consecutive_failures));
+            return;
+        }
+
+        self.gray.remove(&address);
+    }
+
+    /// Returns a dial candidate, or `None` if both tiers are empty or every tracked address is
+    /// still backing off from a recent failure.
+    ///
+    /// A netgroup is chosen uniformly at random among those with at least one tracked,
+    /// not-currently-backed-off address, then an address is chosen uniformly at random within it.
+    /// Selecting by netgroup first, rather than uniformly over all addresses, stops a netgroup
+    /// that has flooded up to its cap from being dialed disproportionately often.
+    pub fn dial_candidate(&self) -> Option<SocketAddr> {
+        let now = self.clock.now();
+        let mut netgroups: HashMap<NetGroup, Vec<SocketAddr>> = HashMap::new();
+
+        for entry in self.white.values().chain(self.gray.values()) {
+            if entry.retry_at.map_or(false, |retry_at| retry_at > now) {
+                continue;
+            }
+
+            netgroups
+                .entry(NetGroup::for_addr(&entry.address))
+                .or_default()
+                .push(entry.address);
+        }
+
+        let candidates = netgroups.values().choose(&mut thread_rng())?;
+
+        candidates.iter().choose(&mut thread_rng()).copied()
+    }
+
+    /// Returns the reconnect backoff to apply after `consecutive_failures` failed attempts.
+    fn backoff(consecutive_failures: usize) -> chrono::Duration {
+        chrono::Duration::from_std(RETRY_BACKOFF_BASE * 2u32.saturating_pow(consecutive_failures as u32 - 1))
+            .expect("backoff duration fits in a chrono::Duration")
+    }
+
+    /// Replace the anchor peers with `addresses`, the peers we're currently connected to.
+    ///
+    /// Only the first [`MAX_ANCHORS`] addresses are kept.
+    pub fn set_anchors(&mut self, addresses: Vec<SocketAddr>) {
+        self.anchors = addresses.into_iter().take(MAX_ANCHORS).collect();
+    }
+
+    /// Returns the current anchor peers, to reconnect to first on restart.
+    pub fn anchors(&self) -> &[SocketAddr] {
+        &self.anchors
+    }
+
+    /// Persist the current anchor peers to `path`, one address per line.
+    pub fn save_anchors(&self, path: &Path) -> io::Result<()> {
+        let contents: String = self
+            .anchors
+            .iter()
+            .map(|address| format!("{}\n", address))
+            .collect();
+
+        fs::write(path, contents)
+    }
+
+    /// Load anchor peers previously written by [`Self::save_anchors`] from `path`.
+    ///
+    /// A missing file just means there are no persisted anchors yet, so it isn't an error.
+    pub fn load_anchors(&mut self, path: &Path) -> io::Result<()> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+
+        self.anchors = contents
+            .lines()
+            .filter_map(|line| line.parse().ok())
+            .take(MAX_ANCHORS)
+            .collect();
+
+        Ok(())
+    }
+
+    /// Returns the number of `tier` entries that share `address`'s netgroup.
+    fn netgroup_count(
+        &self,
+        tier: &HashMap<SocketAddr, AddressEntry>,
+        address: &SocketAddr,
+    ) -> usize {
+        let netgroup = NetGroup::for_addr(address);
+
+        tier.values()
+            .filter(|entry| NetGroup::for_addr(&entry.address) == netgroup)
+            .count()
+    }
+
+    /// Returns the white-listed address in `address`'s netgroup with the most consecutive
+    /// failures, if any, to evict when that netgroup is full.
+    fn worst_in_netgroup(&self, address: &SocketAddr) -> Option<SocketAddr> {
+        let netgroup = NetGroup::for_addr(address);
+
+        self.white
+            .values()
+            .filter(|entry| NetGroup::for_addr(&entry.address) == netgroup)
+            .max_by_key(|entry| entry.consecutive_failures)
+            .map(|entry| entry.address)
+    }
+}
+
+impl AddressEntry {
+    /// Returns a new, untried entry for `address`.
+    fn fresh(address: SocketAddr) -> Self {
+        AddressEntry {
+            address,
+            consecutive_failures: 0,
+            retry_at: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Deterministic reconnect-backoff tests, using [`ScriptedClock`] to advance virtual time in
+    //! controlled steps instead of sleeping real wall-clock time.
+    //!
+    //! TODO: `candidate_set.rs` and `meta_addr.rs` are absent from this tree (only their test
+    //! modules remain), so the gossip-driven, multi-round crawling scenario described in #1871 -
+    //! scripting `MockPeerService` responses alongside virtual time and asserting on
+    //! `CandidateSet::next()`'s exact reconnect sequence - can't be built yet. This harness covers
+    //! the same pluggable-clock idea one layer down, for the one gossip/backoff component that
+    //! does exist here: `AddressBook`'s reconnect backoff.
+
+    use std::{cell::Cell, net::SocketAddr, rc::Rc};
+
+    use chrono::{DateTime, Utc};
+
+    use super::{AddressBook, Clock};
+
+    /// A [`Clock`] whose `now()` is set by the test, rather than tracking wall-clock time.
+    #[derive(Clone)]
+    struct ScriptedClock(Rc<Cell<DateTime<Utc>>>);
+
+    impl ScriptedClock {
+        fn starting_at(now: DateTime<Utc>) -> Self {
+            ScriptedClock(Rc::new(Cell::new(now)))
+        }
+
+        /// Advances virtual time by `duration`.
+        fn advance(&self, duration: chrono::Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    impl Clock for ScriptedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0.get()
+        }
+    }
+
+    /// A failed address shouldn't be offered again until its backoff elapses, and should be
+    /// offered again once it has.
+    #[test]
+    fn failed_address_backs_off_then_becomes_available_again() {
+        let clock = ScriptedClock::starting_at(Utc::now());
+        let mut address_book = AddressBook::with_clock(clock.clone());
+
+        let address: SocketAddr = "192.168.1.1:8233".parse().unwrap();
+        address_book.record_gossiped(address);
+        // Only white-listed addresses back off: a failed gray address is evicted outright.
+        address_book.record_success(address);
+        assert_eq!(address_book.dial_candidate(), Some(address));
+
+        address_book.record_failure(address);
+        assert_eq!(
+            address_book.dial_candidate(),
+            None,
+            "a just-failed address should be backing off"
+        );
+
+        clock.advance(chrono::Duration::seconds(5));
+        assert_eq!(
+            address_book.dial_candidate(),
+            None,
+            "backoff shouldn't have elapsed yet"
+        );
+
+        clock.advance(chrono::Duration::seconds(30));
+        assert_eq!(
+            address_book.dial_candidate(),
+            Some(address),
+            "backoff should have elapsed by now"
+        );
+    }
+
+    /// Each consecutive failure should at least double the previous backoff.
+    #[test]
+    fn backoff_grows_with_consecutive_failures() {
+        let clock = ScriptedClock::starting_at(Utc::now());
+        let mut address_book = AddressBook::with_clock(clock.clone());
+
+        let address: SocketAddr = "192.168.1.1:8233".parse().unwrap();
+        address_book.record_gossiped(address);
+        address_book.record_success(address);
+
+        address_book.record_failure(address);
+        clock.advance(chrono::Duration::seconds(15));
+        assert_eq!(
+            address_book.dial_candidate(),
+            Some(address),
+            "first backoff should already have elapsed"
+        );
+
+        address_book.record_failure(address);
+        clock.advance(chrono::Duration::seconds(15));
+        assert_eq!(
+            address_book.dial_candidate(),
+            None,
+            "second backoff should be longer than the first, and still be in effect"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Per-peer clock-skew estimation for validating gossiped address timestamps.
+//!
+//! [`super::candidate_set::validate_addrs`] (exercised by the test vectors in
+//! `candidate_set/tests/vectors.rs`, e.g. `offsets_last_seen_times_in_the_future`) only ever
+//! corrects a single gossiped batch in isolation: it looks for the largest `last_seen` time in
+//! that batch, and if it's in the future, shifts every address in the batch down by the same
+//! amount. That's a reasonable last-resort safety net, but it throws away what we learn about a
+//! peer across connections - a peer whose clock is persistently 20 minutes fast will trip this
+//! correction on every single gossip round, instead of us simply knowing to expect it.
+//!
+//! [`PeerClockSkew`] tracks a rolling median of each peer's observed skew - the difference between
+//! its self-reported time during the `version` handshake and our own local time - and exposes it
+//! as a per-peer correction to apply before the existing within-batch safety net runs.
+//!
+//! TODO: `candidate_set.rs` and `meta_addr.rs` are absent from this checkout (only their test
+//! modules survive, and so is `zebra_chain::serialization::DateTime32`), so there's no real
+//! `CandidateSet::update` call site or `MetaAddr` to thread this through yet - see the identical
+//! gap noted in [`super::addr_manager`] and in `AddressBook`'s own test module. This module is
+//! written standalone, against plain `chrono` timestamps, ready to be wired into the real
+//! `validate_addrs`/`CandidateSet::update`/version-handshake call sites once those files exist.
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// The number of recent skew samples kept per peer for the rolling median.
+///
+/// An odd number, so a full window has a single middle sample.
+const MAX_SKEW_SAMPLES: usize = 11;
+
+/// A rolling window of clock-skew samples observed from a single peer.
+#[derive(Clone, Debug, Default)]
+struct PeerSkewSamples(Vec<Duration>);
+
+impl PeerSkewSamples {
+    /// Records a new observed `skew`, evicting the oldest sample if the window is full.
+    fn record(&mut self, skew: Duration) {
+        if self.0.len() == MAX_SKEW_SAMPLES {
+            self.0.remove(0);
+        }
+
+        self.0.push(skew);
+    }
+
+    /// Returns the median of the recorded samples (the upper of the two middle samples when there
+    /// is an even number of them), or a zero offset if there are none yet.
+    fn median(&self) -> Duration {
+        if self.0.is_empty() {
+            return Duration::zero();
+        }
+
+        let mut millis: Vec<i64> = self.0.iter().map(Duration::num_milliseconds).collect();
+        millis.sort_unstable();
+
+        Duration::milliseconds(millis[millis.len() / 2])
+    }
+}
+
+/// Tracks each peer's estimated clock skew across connections.
+///
+/// A positive skew means the peer's clock runs ahead of ours; a negative skew means it runs
+/// behind. The estimate is the median of up to [`MAX_SKEW_SAMPLES`] recent observations, so a
+/// single noisy handshake (or a single attempt at manipulation) can't swing the correction that
+/// later gossip from the same peer gets validated against.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PeerClockSkew {
+    samples: HashMap<SocketAddr, PeerSkewSamples>,
+}
+
+impl PeerClockSkew {
+    /// Returns a new, empty [`PeerClockSkew`] tracker.
+    pub(crate) fn new() -> Self {
+        PeerClockSkew::default()
+    }
+
+    /// Records an observed clock-skew sample for `peer`, taken during its `version` handshake:
+    /// the difference between `peer`'s self-reported current time and our own local time.
+    pub(crate) fn record_observation(
+        &mut self,
+        peer: SocketAddr,
+        reported_now: DateTime<Utc>,
+        local_now: DateTime<Utc>,
+    ) {
+        let skew = reported_now - local_now;
+
+        self.samples.entry(peer).or_default().record(skew);
+    }
+
+    /// Returns `peer`'s current estimated clock skew: the median of its recent samples, or zero
+    /// if we haven't observed a handshake from it yet.
+    pub(crate) fn estimated_skew(&self, peer: &SocketAddr) -> Duration {
+        self.samples
+            .get(peer)
+            .map(PeerSkewSamples::median)
+            .unwrap_or_else(Duration::zero)
+    }
+}
+
+/// Corrects `last_seen_times` gossiped by `peer`, applying both `peer`'s persistent estimated
+/// skew and the existing within-batch future-time safety net.
+///
+/// First, each time has `peer`'s persistent skew (as tracked by [`PeerClockSkew`]) subtracted, so
+/// a peer with a known-fast or known-slow clock is corrected using what we've learned about it
+/// over many connections, not just this one gossiped batch. Then, as a safety net against skew we
+/// haven't learned yet (or a peer actively lying about the current time), the existing rule still
+/// applies: if the most recent corrected time is still after `last_seen_limit`, every time in the
+/// batch is shifted down by that remaining excess.
+///
+/// Returns `None` if applying either correction would underflow `DateTime<Utc>`'s representable
+/// range, in which case every address in the batch should be rejected rather than served with a
+/// bogus timestamp - the same behavior [`super::candidate_set::validate_addrs`]'s test vectors
+/// (`rejects_all_addresses_if_applying_offset_causes_an_underflow`) expect of the batch-local case.
+pub(crate) fn correct_for_peer_skew(
+    last_seen_times: impl IntoIterator<Item = DateTime<Utc>>,
+    last_seen_limit: DateTime<Utc>,
+    persistent_skew: Duration,
+) -> Option<Vec<DateTime<Utc>>> {
+    let skew_corrected: Vec<DateTime<Utc>> = last_seen_times
+        .into_iter()
+        .map(|last_seen| last_seen.checked_sub_signed(persistent_skew))
+        .collect::<Option<_>>()?;
+
+    let most_recent = *skew_corrected.iter().max()?;
+
+    if most_recent <= last_seen_limit {
+        return Some(skew_corrected);
+    }
+
+    let remaining_offset = most_recent - last_seen_limit;
+
+    skew_corrected
+        .into_iter()
+        .map(|last_seen| last_seen.checked_sub_signed(remaining_offset))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(base: DateTime<Utc>, offsets_minutes: &[i64]) -> Vec<DateTime<Utc>> {
+        offsets_minutes
+            .iter()
+            .map(|minutes| base + Duration::minutes(*minutes))
+            .collect()
+    }
+
+    /// A single wild outlier doesn't move the estimate: of these six samples, the upper of the two
+    /// middle samples once sorted is returned.
+    #[test]
+    fn median_skew_ignores_a_single_outlier() {
+        let mut skew = PeerClockSkew::new();
+        let peer: SocketAddr = "192.168.1.1:8233".parse().unwrap();
+        let local_now = Utc::now();
+
+        // Five observations clustered around +10 minutes of skew, and one wild outlier.
+        for minutes in [9, 10, 10, 11, 10] {
+            skew.record_observation(peer, local_now + Duration::minutes(minutes), local_now);
+        }
+        skew.record_observation(peer, local_now + Duration::hours(5), local_now);
+
+        assert_eq!(skew.estimated_skew(&peer), Duration::minutes(10));
+    }
+
+    /// A peer we've never observed a handshake from has no estimated skew.
+    #[test]
+    fn unknown_peer_has_zero_skew() {
+        let skew = PeerClockSkew::new();
+        let peer: SocketAddr = "192.168.1.1:8233".parse().unwrap();
+
+        assert_eq!(skew.estimated_skew(&peer), Duration::zero());
+    }
+
+    /// A peer with a persistent positive skew (its clock runs fast) should have every gossiped
+    /// time corrected down by that skew, even though none of them are individually implausible
+    /// relative to each other within the batch.
+    #[test]
+    fn corrects_persistent_positive_skew() {
+        let last_seen_limit = Utc::now();
+        let persistent_skew = Duration::minutes(20);
+
+        let input = times(last_seen_limit, &[-60, -90, -120]);
+        let corrected =
+            correct_for_peer_skew(input.clone(), last_seen_limit, persistent_skew).unwrap();
+
+        let expected: Vec<_> = input
+            .into_iter()
+            .map(|time| time - persistent_skew)
+            .collect();
+
+        assert_eq!(corrected, expected);
+    }
+
+    /// A peer with a persistent negative skew (its clock runs slow) should have every gossiped
+    /// time corrected *up* by the magnitude of that skew.
+    #[test]
+    fn corrects_persistent_negative_skew() {
+        let last_seen_limit = Utc::now();
+        let persistent_skew = Duration::minutes(-30);
+
+        let input = times(last_seen_limit, &[-60, -90, -120]);
+        let corrected =
+            correct_for_peer_skew(input.clone(), last_seen_limit, persistent_skew).unwrap();
+
+        let expected: Vec<_> = input
+            .into_iter()
+            .map(|time| time - persistent_skew)
+            .collect();
+
+        assert_eq!(corrected, expected);
+    }
+
+    /// Even after correcting for a peer's known persistent skew, a batch that's still in the
+    /// future relative to `last_seen_limit` is additionally corrected by the leftover offset.
+    #[test]
+    fn applies_batch_safety_net_after_persistent_skew_correction() {
+        let last_seen_limit = Utc::now();
+        // We think this peer's clock runs 10 minutes fast, but this batch is 40 minutes ahead of
+        // that expectation - some combination of further skew drift or active manipulation.
+        let persistent_skew = Duration::minutes(10);
+
+        let input = times(last_seen_limit, &[50, 35, 20]);
+        let corrected = correct_for_peer_skew(input, last_seen_limit, persistent_skew).unwrap();
+
+        assert_eq!(
+            corrected.iter().max().copied(),
+            Some(last_seen_limit),
+            "the most recent corrected time should land exactly on the limit"
+        );
+    }
+
+    /// Rejects the whole batch if correcting for an extreme persistent skew would underflow the
+    /// representable range of `DateTime<Utc>`, rather than silently producing a bogus timestamp.
+    #[test]
+    fn rejects_batch_on_underflow() {
+        let last_seen_limit = Utc::now();
+        let persistent_skew = Duration::max_value();
+
+        let input = times(last_seen_limit, &[0, -5]);
+
+        assert!(correct_for_peer_skew(input, last_seen_limit, persistent_skew).is_none());
+    }
+}
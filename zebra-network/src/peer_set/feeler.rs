@@ -0,0 +1,115 @@
+//! Feeler connections: short-lived probes that check whether a "new" address in
+//! [`super::addr_manager::AddrManager`] is actually reachable, and record the outcome there.
+//! Probing doesn't gate dialing: `AddrManager::select` can return a "new" address that no feeler
+//! has probed yet.
+//!
+//! Unlike the rate-limited `addr` gossip fetched through `CandidateSet::update` (see
+//! [`super::candidate_set`]'s `MIN_PEER_GET_ADDR_INTERVAL`), feelers run on their own, much slower
+//! [`FEELER_INTERVAL`], and only ever touch one address per tick: the point isn't to discover new
+//! addresses, it's to confirm or refute reachability for addresses we already know about but have
+//! never successfully connected to.
+//!
+//! TODO: `candidate_set.rs` is absent from this checkout (see the identical note in
+//! [`super::addr_manager`]), so there's no `Request::Peers` sanitization pass for
+//! [`AddrManager::is_known_unreachable`] to filter against yet, and `meta_addr.rs` (which would
+//! define `was_recently_reachable`, per its test module at `meta_addr/tests/vectors.rs`) is
+//! likewise absent. This task is written standalone against the real [`Connector`] service and
+//! [`Client::shutdown`], ready to be wired into that sanitization pass once those files exist.
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tower::{discover::Change, BoxError, Service, ServiceExt};
+
+use super::addr_manager::AddrManager;
+use super::address_book::Clock;
+use crate::peer::Client;
+
+/// How often a feeler connection is attempted, independent of `addr` gossip fetches.
+///
+/// Deliberately much slower than a real dial attempt: feelers exist to slowly keep the "new"
+/// table honest, not to grow the "tried" table quickly.
+pub const FEELER_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+/// How long a feeler connection is given to complete its handshake and confirm reachability,
+/// before it's treated as unreachable.
+const FEELER_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a successful feeler connection is given to shut down gracefully before being forced
+/// closed - there's nothing further to do with it once reachability is confirmed.
+const FEELER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs feeler connections forever, probing one "new" address from `addr_manager` every
+/// [`FEELER_INTERVAL`] via `connector`.
+///
+/// Returns only if `connector`'s service becomes permanently unavailable.
+pub(crate) async fn run<C, Clk>(
+    addr_manager: Arc<Mutex<AddrManager<Clk>>>,
+    mut connector: C,
+) -> Result<(), BoxError>
+where
+    C: Service<SocketAddr, Response = Change<SocketAddr, Client>, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    C::Future: Send,
+    Clk: Clock,
+{
+    loop {
+        tokio::time::sleep(FEELER_INTERVAL).await;
+
+        let address = {
+            let addr_manager = addr_manager.lock().expect("addr manager mutex should be unpoisoned");
+            addr_manager.select_new()
+        };
+
+        let address = match address {
+            Some(address) => address,
+            // Nothing untried to probe this round; try again next tick.
+            None => continue,
+        };
+
+        probe(&addr_manager, &mut connector, address).await?;
+    }
+}
+
+/// Probes a single `address` for reachability, recording the outcome in `addr_manager`.
+async fn probe<C, Clk>(
+    addr_manager: &Arc<Mutex<AddrManager<Clk>>>,
+    connector: &mut C,
+    address: SocketAddr,
+) -> Result<(), BoxError>
+where
+    C: Service<SocketAddr, Response = Change<SocketAddr, Client>, Error = BoxError> + Send,
+    C::Future: Send,
+    Clk: Clock,
+{
+    let ready_connector = connector.ready().await?;
+
+    let outcome = tokio::time::timeout(FEELER_HANDSHAKE_TIMEOUT, ready_connector.call(address)).await;
+
+    let mut addr_manager = addr_manager.lock().expect("addr manager mutex should be unpoisoned");
+
+    match outcome {
+        Ok(Ok(Change::Insert(_, client))) => {
+            addr_manager.record_success(address);
+            // We only opened this connection to confirm reachability: close it straight away
+            // rather than leaving it idle and untracked by the rest of the peer set.
+            drop(addr_manager);
+            client.shutdown(FEELER_SHUTDOWN_TIMEOUT).await;
+        }
+        Ok(Ok(Change::Remove(_))) => {
+            // A feeler connection attempt can't observe a removal; treat it the same as an
+            // outright connection failure rather than silently dropping the sample.
+            addr_manager.record_failure(address);
+        }
+        Ok(Err(_)) | Err(_) => {
+            addr_manager.record_failure(address);
+        }
+    }
+
+    Ok(())
+}
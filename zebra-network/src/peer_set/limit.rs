@@ -0,0 +1,51 @@
+//! Limits on the number of active peer connections.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// A shared counter of the number of active peer connections.
+///
+/// Each live [`ConnectionTracker`] handed out by [`ActiveConnectionCounter::track_connection`]
+/// keeps the counter incremented; dropping it decrements the counter again.
+#[derive(Clone, Debug, Default)]
+pub struct ActiveConnectionCounter {
+    count: Arc<AtomicUsize>,
+}
+
+impl ActiveConnectionCounter {
+    /// Create a new, empty [`ActiveConnectionCounter`].
+    pub fn new_counter() -> Self {
+        ActiveConnectionCounter::default()
+    }
+
+    /// Returns the number of currently active connections tracked by this counter.
+    pub fn update_count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Start tracking a new connection, returning a [`ConnectionTracker`] that releases the slot
+    /// when it is dropped.
+    pub fn track_connection(&self) -> ConnectionTracker {
+        self.count.fetch_add(1, Ordering::SeqCst);
+
+        ConnectionTracker {
+            count: self.count.clone(),
+        }
+    }
+}
+
+/// A handle that keeps a single connection slot reserved in an [`ActiveConnectionCounter`].
+///
+/// The slot is released when this tracker is dropped.
+#[derive(Debug)]
+pub struct ConnectionTracker {
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionTracker {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
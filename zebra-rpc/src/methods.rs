@@ -6,11 +6,37 @@
 //! Some parts of the `zcashd` RPC documentation are outdated.
 //! So this implementation follows the `lightwalletd` client implementation.
 
-use jsonrpc_core::{self, Result};
+use std::sync::Arc;
+
+use futures::{FutureExt, TryFutureExt};
+use jsonrpc_core::{self, BoxFuture, Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
+use tower::{Service, ServiceExt};
 
+use zebra_chain::{
+    block,
+    parameters::Network,
+    serialization::{ZcashDeserializeInto, ZcashSerialize},
+    transaction::{self, Transaction, UnminedTx, UnminedTxId},
+};
 use zebra_network::constants::USER_AGENT;
 use zebra_node_services::{mempool, BoxError};
+use zebra_state::BestTipHeightReceiver;
+
+/// The RPC error code used by `zcashd` for deserialization errors, e.g. a malformed raw
+/// transaction.
+const RPC_DESERIALIZATION_ERROR_CODE: i64 = -22;
+
+/// The RPC error code used by `zcashd` when a transaction is rejected by mempool policy, rather
+/// than being malformed.
+const RPC_VERIFY_REJECTED_CODE: i64 = -26;
+
+/// The RPC error code used by `zcashd` when a requested block or transaction can't be found.
+const RPC_NOT_FOUND_ERROR_CODE: i64 = -5;
+
+/// The RPC error code used by `zcashd` when there's no best block yet, e.g. before the genesis
+/// block has been committed.
+const RPC_IN_WARMUP_ERROR_CODE: i64 = -28;
 
 #[cfg(test)]
 mod tests;
@@ -43,12 +69,12 @@ pub trait Rpc {
 
     /// getblockchaininfo
     ///
-    /// TODO: explain what the method does
-    ///       link to the zcashd RPC reference
-    ///       list the arguments and fields that lightwalletd uses
-    ///       note any other lightwalletd changes
+    /// Returns blockchain state and consensus upgrade information, as used by `lightwalletd` to
+    /// report sync progress and pick the right consensus branch id for new transactions.
+    ///
+    /// zcashd reference: <https://zcash.github.io/rpc/getblockchaininfo.html>
     #[rpc(name = "getblockchaininfo")]
-    fn get_blockchain_info(&self) -> Result<GetBlockChainInfo>;
+    fn get_blockchain_info(&self) -> BoxFuture<Result<GetBlockChainInfo>>;
 
     /// Send a raw signed transaction.
     ///
@@ -58,42 +84,137 @@ pub trait Rpc {
     /// [`sendrawtransaction`](https://zcash.github.io/rpc/sendrawtransaction.html) documentation
     /// for more information.
     #[rpc(name = "sendrawtransaction")]
-    fn send_raw_transaction(&self, raw_transaction_hex: String) -> Result<SentTransactionHash>;
+    fn send_raw_transaction(
+        &self,
+        raw_transaction_hex: String,
+    ) -> BoxFuture<Result<SentTransactionHash>>;
+
+    /// getbestblockhash
+    ///
+    /// Returns the hash of the current best block.
+    ///
+    /// zcashd reference: <https://zcash.github.io/rpc/getbestblockhash.html>
+    #[rpc(name = "getbestblockhash")]
+    fn get_best_block_hash(&self) -> BoxFuture<Result<String>>;
+
+    /// getblock
+    ///
+    /// Returns the requested block by hash or height, as used by `lightwalletd` to sync the
+    /// chain.
+    ///
+    /// zcashd reference: <https://zcash.github.io/rpc/getblock.html>
+    ///
+    /// # Parameters
+    ///
+    /// - `hash_or_height`: (string, required) The block hash or height.
+    /// - `verbosity`: (number, optional, default=1) 0 for the raw block hex, 1 for a decoded
+    ///   JSON object.
+    #[rpc(name = "getblock")]
+    fn get_block(
+        &self,
+        hash_or_height: String,
+        verbosity: Option<u8>,
+    ) -> BoxFuture<Result<GetBlock>>;
+
+    /// getrawtransaction
+    ///
+    /// Returns the requested transaction, looking it up in the state first and falling back to
+    /// the mempool for transactions that haven't been mined yet.
+    ///
+    /// zcashd reference: <https://zcash.github.io/rpc/getrawtransaction.html>
+    ///
+    /// # Parameters
+    ///
+    /// - `txid`: (string, required) The transaction id, as a hex-encoded, byte-reversed hash.
+    /// - `verbose`: (number, optional, default=0) 0 for the raw transaction hex, 1 for a decoded
+    ///   JSON object.
+    #[rpc(name = "getrawtransaction")]
+    fn get_raw_transaction(
+        &self,
+        txid: String,
+        verbose: Option<u8>,
+    ) -> BoxFuture<Result<GetRawTransaction>>;
 }
 
 /// RPC method implementations.
-pub struct RpcImpl<Mempool> {
+pub struct RpcImpl<Mempool, State> {
     /// Zebra's application version.
     app_version: String,
 
+    /// The configured network, used to report the chain name and consensus upgrade heights.
+    network: Network,
+
     /// A handle to the mempool service.
     ///
     /// Used when sending raw transactions.
     mempool: Mempool,
+
+    /// A handle to the state service.
+    ///
+    /// Used for queries that can be answered purely from state, like the current tip or a
+    /// specific block or transaction.
+    state: State,
+
+    /// The polled network tip height, used to report a genuine
+    /// [`GetBlockChainInfo::estimated_height`] instead of a value hardcoded to [`Self::state`]'s
+    /// own tip.
+    ///
+    /// `None` when no [`rpc_tip_poller`][zebra_state::rpc_tip_poller] task has been wired up for
+    /// this RPC server, in which case `estimated_height` falls back to the local tip height.
+    best_tip_height: Option<BestTipHeightReceiver>,
 }
 
-impl<Mempool> RpcImpl<Mempool>
+impl<Mempool, State> RpcImpl<Mempool, State>
 where
     Mempool: tower::Service<mempool::Request, Response = mempool::Response, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    Mempool::Future: Send,
+    State: tower::Service<zebra_state::Request, Response = zebra_state::Response, Error = BoxError>
+        + Clone
         + Send
         + Sync
         + 'static,
+    State::Future: Send,
 {
     /// Create a new instance of the RPC handler.
-    pub fn new(app_version: String, mempool: Mempool) -> Self {
+    ///
+    /// `best_tip_height` is `None` when the caller hasn't wired up an
+    /// [`rpc_tip_poller`][zebra_state::rpc_tip_poller] task, in which case
+    /// [`GetBlockChainInfo::estimated_height`] falls back to the local tip height.
+    pub fn new(
+        app_version: String,
+        network: Network,
+        mempool: Mempool,
+        state: State,
+        best_tip_height: Option<BestTipHeightReceiver>,
+    ) -> Self {
         RpcImpl {
             app_version,
+            network,
             mempool,
+            state,
+            best_tip_height,
         }
     }
 }
 
-impl<Mempool> Rpc for RpcImpl<Mempool>
+impl<Mempool, State> Rpc for RpcImpl<Mempool, State>
 where
     Mempool: tower::Service<mempool::Request, Response = mempool::Response, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    Mempool::Future: Send,
+    State: tower::Service<zebra_state::Request, Response = zebra_state::Response, Error = BoxError>
+        + Clone
         + Send
         + Sync
         + 'static,
+    State::Future: Send,
 {
     fn get_info(&self) -> Result<GetInfo> {
         let response = GetInfo {
@@ -104,17 +225,352 @@ where
         Ok(response)
     }
 
-    fn get_blockchain_info(&self) -> Result<GetBlockChainInfo> {
-        // TODO: dummy output data, fix in the context of #3143
-        let response = GetBlockChainInfo {
-            chain: "TODO: main".to_string(),
-        };
+    fn get_blockchain_info(&self) -> BoxFuture<Result<GetBlockChainInfo>> {
+        let mut state = self.state.clone();
+        let network = self.network;
+        let best_tip_height = self.best_tip_height.clone();
 
-        Ok(response)
+        async move {
+            let response = state
+                .ready_and()
+                .and_then(|state| state.call(zebra_state::Request::Tip))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let (height, hash) = match response {
+                zebra_state::Response::Tip(tip) => {
+                    tip.unwrap_or((block::Height(0), block::Hash([0; 32])))
+                }
+                _ => unreachable!("zebra_state::Request::Tip always returns Response::Tip"),
+            };
+
+            let upgrades = Network::activation_list(network)
+                .into_iter()
+                .filter_map(|(activation_height, upgrade)| {
+                    let branch_id = upgrade.branch_id()?;
+
+                    Some((
+                        format!("{:08x}", branch_id),
+                        NetworkUpgradeInfo {
+                            name: format!("{:?}", upgrade),
+                            activation_height: activation_height.0,
+                            status: if height >= activation_height {
+                                "active".to_string()
+                            } else {
+                                "pending".to_string()
+                            },
+                        },
+                    ))
+                })
+                .collect();
+
+            // Only trust the polled network tip when it's actually ahead of us: a stale or
+            // negative gap (e.g. right after a restart, before the first successful poll refreshes
+            // it) isn't a better estimate than our own tip.
+            let estimated_height = best_tip_height
+                .as_ref()
+                .and_then(BestTipHeightReceiver::estimated_distance_behind_tip)
+                .filter(|distance_behind_tip| *distance_behind_tip > 0)
+                .and_then(|distance_behind_tip| u32::try_from(distance_behind_tip).ok())
+                .map(|distance_behind_tip| height.0.saturating_add(distance_behind_tip))
+                .unwrap_or(height.0);
+
+            Ok(GetBlockChainInfo {
+                chain: match network {
+                    Network::Mainnet => "main".to_string(),
+                    Network::Testnet => "test".to_string(),
+                },
+                blocks: height.0,
+                best_block_hash: hash.to_string(),
+                estimated_height,
+                upgrades,
+            })
+        }
+        .boxed()
+    }
+
+    fn send_raw_transaction(
+        &self,
+        raw_transaction_hex: String,
+    ) -> BoxFuture<Result<SentTransactionHash>> {
+        let mut mempool = self.mempool.clone();
+
+        async move {
+            let raw_transaction_bytes = hex::decode(raw_transaction_hex).map_err(|error| Error {
+                code: ErrorCode::ServerError(RPC_DESERIALIZATION_ERROR_CODE),
+                message: format!("raw transaction is not valid hex: {}", error),
+                data: None,
+            })?;
+
+            let transaction: Transaction =
+                raw_transaction_bytes
+                    .zcash_deserialize_into()
+                    .map_err(|error| Error {
+                        code: ErrorCode::ServerError(RPC_DESERIALIZATION_ERROR_CODE),
+                        message: format!("raw transaction is structurally invalid: {}", error),
+                        data: None,
+                    })?;
+
+            let unmined_transaction = UnminedTx::from(Arc::new(transaction));
+            let transaction_hash = unmined_transaction.id.mined_id();
+
+            let request = mempool::Request::Queue(vec![mempool::Gossip::Tx(unmined_transaction)]);
+
+            let response = mempool
+                .ready_and()
+                .and_then(|mempool| mempool.call(request))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(RPC_VERIFY_REJECTED_CODE),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let mut results = match response {
+                mempool::Response::Queued(results) => results,
+                _ => unreachable!(
+                    "mempool::Request::Queue always returns mempool::Response::Queued"
+                ),
+            };
+
+            let result = results
+                .pop()
+                .expect("queuing a single transaction returns a single result");
+
+            result.map_err(|error| Error {
+                code: ErrorCode::ServerError(RPC_VERIFY_REJECTED_CODE),
+                message: format!("transaction was not accepted by the mempool: {}", error),
+                data: None,
+            })?;
+
+            Ok(SentTransactionHash(transaction_hash.to_string()))
+        }
+        .boxed()
+    }
+
+    fn get_best_block_hash(&self) -> BoxFuture<Result<String>> {
+        let mut state = self.state.clone();
+
+        async move {
+            let response = state
+                .ready_and()
+                .and_then(|state| state.call(zebra_state::Request::Tip))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let tip = match response {
+                zebra_state::Response::Tip(tip) => tip,
+                _ => unreachable!("zebra_state::Request::Tip always returns Response::Tip"),
+            };
+
+            let (_height, hash) = tip.ok_or_else(|| Error {
+                code: ErrorCode::ServerError(RPC_IN_WARMUP_ERROR_CODE),
+                message: "the node has no best block yet".to_string(),
+                data: None,
+            })?;
+
+            Ok(hash.to_string())
+        }
+        .boxed()
+    }
+
+    fn get_block(
+        &self,
+        hash_or_height: String,
+        verbosity: Option<u8>,
+    ) -> BoxFuture<Result<GetBlock>> {
+        let mut state = self.state.clone();
+
+        async move {
+            let hash_or_height: block::HashOrHeight =
+                hash_or_height.parse().map_err(|error| Error {
+                    code: ErrorCode::ServerError(RPC_DESERIALIZATION_ERROR_CODE),
+                    message: format!("error parsing hash or height: {}", error),
+                    data: None,
+                })?;
+
+            let tip_response = state
+                .ready_and()
+                .and_then(|state| state.call(zebra_state::Request::Tip))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let tip_height = match tip_response {
+                zebra_state::Response::Tip(tip) => tip.map(|(height, _hash)| height),
+                _ => unreachable!("zebra_state::Request::Tip always returns Response::Tip"),
+            };
+
+            let block_response = state
+                .ready_and()
+                .and_then(|state| state.call(zebra_state::Request::Block(hash_or_height)))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let block = match block_response {
+                zebra_state::Response::Block(block) => block,
+                _ => unreachable!("zebra_state::Request::Block always returns Response::Block"),
+            };
+
+            let block = block.ok_or_else(|| Error {
+                code: ErrorCode::ServerError(RPC_NOT_FOUND_ERROR_CODE),
+                message: "block not found".to_string(),
+                data: None,
+            })?;
+
+            if verbosity == Some(0) {
+                let raw_block_bytes = block.zcash_serialize_to_vec().map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+                return Ok(GetBlock::Raw(hex::encode(raw_block_bytes)));
+            }
+
+            let height = block
+                .coinbase_height()
+                .expect("committed blocks have a coinbase height");
+
+            // Matches zcashd, which reports a block not yet known to be on the best chain as
+            // having zero confirmations.
+            let confirmations = tip_height
+                .filter(|tip_height| *tip_height >= height)
+                .map(|tip_height| tip_height.0 - height.0 + 1)
+                .unwrap_or(0);
+
+            Ok(GetBlock::Object(Box::new(BlockObject {
+                hash: block.hash().to_string(),
+                confirmations,
+                height: height.0,
+                time: block.header.time.timestamp(),
+                tx: block
+                    .transactions
+                    .iter()
+                    .map(|transaction| transaction.hash().to_string())
+                    .collect(),
+            })))
+        }
+        .boxed()
     }
 
-    fn send_raw_transaction(&self, raw_transaction_hex: String) -> Result<SentTransactionHash> {
-        todo!();
+    fn get_raw_transaction(
+        &self,
+        txid: String,
+        verbose: Option<u8>,
+    ) -> BoxFuture<Result<GetRawTransaction>> {
+        let mut state = self.state.clone();
+        let mut mempool = self.mempool.clone();
+
+        async move {
+            let hash: transaction::Hash = txid.parse().map_err(|error| Error {
+                code: ErrorCode::ServerError(RPC_DESERIALIZATION_ERROR_CODE),
+                message: format!("error parsing txid: {}", error),
+                data: None,
+            })?;
+
+            let response = state
+                .ready_and()
+                .and_then(|state| state.call(zebra_state::Request::Transaction(hash)))
+                .await
+                .map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            let mined_transaction = match response {
+                zebra_state::Response::Transaction(transaction) => transaction,
+                _ => unreachable!(
+                    "zebra_state::Request::Transaction always returns Response::Transaction"
+                ),
+            };
+
+            // `height`/`confirmations` are left unset for a mined transaction: doing better
+            // would need a state request that returns the containing block's height alongside
+            // the transaction, and `Request::Transaction` doesn't carry one.
+            let (transaction, height, confirmations) = match mined_transaction {
+                Some(transaction) => (transaction, None, None),
+                None => {
+                    // TODO: `UnminedTxId`'s exact constructor from a legacy `transaction::Hash`
+                    // isn't confirmed in this tree (only its `mined_id` accessor is, via
+                    // `send_raw_transaction` above) - this assumes a `From<transaction::Hash>`
+                    // impl exists for the common pre-NU5, non-witnessed case.
+                    let txid = UnminedTxId::from(hash);
+                    let request =
+                        mempool::Request::TransactionsById(std::iter::once(txid).collect());
+
+                    let response = mempool
+                        .ready_and()
+                        .and_then(|mempool| mempool.call(request))
+                        .await
+                        .map_err(|error| Error {
+                            code: ErrorCode::ServerError(0),
+                            message: error.to_string(),
+                            data: None,
+                        })?;
+
+                    let mut transactions = match response {
+                        mempool::Response::Transactions(transactions) => transactions,
+                        _ => unreachable!(
+                            "mempool::Request::TransactionsById always returns \
+                             mempool::Response::Transactions"
+                        ),
+                    };
+
+                    let unmined_transaction = transactions.pop().ok_or_else(|| Error {
+                        code: ErrorCode::ServerError(RPC_NOT_FOUND_ERROR_CODE),
+                        message:
+                            "transaction not found in the state or the mempool".to_string(),
+                        data: None,
+                    })?;
+
+                    // Zero confirmations is `zcashd`'s convention for an unmined transaction.
+                    (unmined_transaction.transaction, None, Some(0))
+                }
+            };
+
+            if verbose != Some(1) {
+                let raw_transaction_bytes =
+                    transaction.zcash_serialize_to_vec().map_err(|error| Error {
+                        code: ErrorCode::ServerError(0),
+                        message: error.to_string(),
+                        data: None,
+                    })?;
+
+                return Ok(GetRawTransaction::Raw(hex::encode(raw_transaction_bytes)));
+            }
+
+            let raw_transaction_bytes =
+                transaction.zcash_serialize_to_vec().map_err(|error| Error {
+                    code: ErrorCode::ServerError(0),
+                    message: error.to_string(),
+                    data: None,
+                })?;
+
+            Ok(GetRawTransaction::Object(Box::new(TransactionObject {
+                hex: hex::encode(raw_transaction_bytes),
+                txid: hash.to_string(),
+                height: height.map(|height: block::Height| height.0),
+                confirmations: confirmations.unwrap_or(0),
+            })))
+        }
+        .boxed()
     }
 }
 
@@ -128,8 +584,41 @@ pub struct GetInfo {
 #[derive(serde::Serialize, serde::Deserialize)]
 /// Response to a `getblockchaininfo` RPC request.
 pub struct GetBlockChainInfo {
+    /// The current network name, as defined in BIP70 (`main`, `test`, or `regtest`).
     chain: String,
-    // TODO: add other fields used by lightwalletd (#3143)
+
+    /// The current number of blocks processed in the server.
+    blocks: u32,
+
+    /// The hash of the current best block.
+    #[serde(rename = "bestblockhash")]
+    best_block_hash: String,
+
+    /// An estimate of the number of blocks in the network's longest chain.
+    ///
+    /// Sourced from [`RpcImpl::best_tip_height`]'s polled network tip, when one is configured,
+    /// has completed at least one successful poll, and is ahead of the local tip; otherwise falls
+    /// back to [`Self::blocks`], the
+    /// same way a freshly started node with no peers yet would.
+    #[serde(rename = "estimatedheight")]
+    estimated_height: u32,
+
+    /// Status of network upgrades, keyed by consensus branch id.
+    upgrades: std::collections::HashMap<String, NetworkUpgradeInfo>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+/// Status of a single network upgrade, as reported in [`GetBlockChainInfo::upgrades`].
+pub struct NetworkUpgradeInfo {
+    /// Branch name.
+    name: String,
+
+    /// Activation height for this upgrade.
+    #[serde(rename = "activationheight")]
+    activation_height: u32,
+
+    /// Status of the upgrade, either `"active"` or `"pending"`.
+    status: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -137,3 +626,74 @@ pub struct GetBlockChainInfo {
 ///
 /// A JSON string with the transaction hash in hexadecimal.
 pub struct SentTransactionHash(String);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+/// Response to a `getblock` RPC request.
+///
+/// Either the raw block hex (`verbosity` 0) or a decoded [`BlockObject`] (`verbosity` 1).
+pub enum GetBlock {
+    /// The raw block, as a hex-encoded string.
+    Raw(String),
+
+    /// The block, deserialized into JSON.
+    Object(Box<BlockObject>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+/// A decoded block, as returned by `getblock` with `verbosity` 1.
+pub struct BlockObject {
+    /// The hash of the requested block.
+    hash: String,
+
+    /// The number of confirmations of this block in the best chain, or 0 if it's not in the
+    /// best chain.
+    confirmations: u32,
+
+    /// The height of the requested block.
+    height: u32,
+
+    /// The block header's timestamp, in seconds since the Unix epoch.
+    time: i64,
+
+    /// The transaction ids in the block, in the order they appear in it.
+    tx: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+/// Response to a `getrawtransaction` RPC request.
+///
+/// Either the raw transaction hex (`verbose` 0, the default) or a decoded [`TransactionObject`]
+/// (`verbose` 1).
+pub enum GetRawTransaction {
+    /// The raw transaction, as a hex-encoded string.
+    Raw(String),
+
+    /// The transaction, deserialized into JSON.
+    Object(Box<TransactionObject>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+/// A decoded transaction, as returned by `getrawtransaction` with `verbose` 1.
+pub struct TransactionObject {
+    /// The raw transaction, as a hex-encoded string.
+    hex: String,
+
+    /// The transaction id.
+    txid: String,
+
+    /// The height of the block containing this transaction, or `None` if it hasn't been mined.
+    ///
+    /// TODO: always `None` for now - populating it needs a state request that returns the
+    /// containing block's height alongside the transaction, which `Request::Transaction`
+    /// doesn't provide in this tree.
+    height: Option<u32>,
+
+    /// The number of confirmations of the block containing this transaction, or 0 if it's
+    /// unmined.
+    ///
+    /// This is also 0 for a mined transaction, since the containing block's height isn't looked
+    /// up (see `height`).
+    confirmations: u32,
+}
@@ -0,0 +1,170 @@
+//! State import and per-column-family size benchmarks, modeled on Substrate's
+//! `node-bench` state/trie/import benchmarks.
+//!
+//! Two things live here:
+//! - a criterion throughput benchmark that imports a fixed range of mainnet blocks into an
+//!   ephemeral [`FinalizedState`], reported separately per chunk size, so a commit-path slowdown
+//!   shows up as a benchmark regression instead of a surprise in production. Every block is
+//!   still committed on its own, so the chunk size doesn't change how writes reach the database;
+//! - [`column_family_report`], a one-shot function that reads an already-open [`FinalizedState`]
+//!   (such as the ephemeral one this benchmark populates; it doesn't open a database directory
+//!   by path) and prints per-column-family entry counts and average entry sizes, so maintainers
+//!   can catch column-family bloat - for example from shielded data growth - before a release.
+//!
+//! This turns `disk_format::tests::snapshot::test_raw_rocksdb_column_family_data`'s fixed test
+//! vectors into a repeatable performance and storage-regression tool.
+//!
+//! TODO: this crate has no `Cargo.toml` in this checkout, so `criterion`/`tempfile` aren't
+//! declared as dev-dependencies, this file isn't registered as a `[[bench]]` target, and
+//! `cargo bench` can't actually run it here. It's written against the real `FinalizedState` and
+//! `Config` APIs (as used by `disk_format::tests::snapshot`) so it only needs that wiring, not a
+//! rewrite, once the manifest exists.
+
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use zebra_chain::{block::Block, parameters::Network, serialization::ZcashDeserializeInto};
+
+use zebra_state::{service::finalized_state::FinalizedState, Config};
+
+/// The chunk sizes (in blocks) the throughput benchmark reports separately.
+///
+/// Every block is committed on its own whatever the chunk size; these only group the commit loop.
+const BATCH_SIZES: &[usize] = &[1, 10, 100];
+
+/// The number of mainnet blocks imported by each benchmark iteration.
+///
+/// Kept well within `zebra_test::vectors::MAINNET_BLOCKS`'s available range, so this doesn't
+/// need the full mainnet history to be vendored just to run the benchmark.
+const IMPORT_BLOCK_COUNT: usize = 500;
+
+/// Returns the first `count` mainnet blocks, in height order, for use as benchmark input.
+fn mainnet_import_blocks(count: usize) -> Vec<std::sync::Arc<Block>> {
+    zebra_test::vectors::MAINNET_BLOCKS
+        .range(..)
+        .take(count)
+        .map(|(_, block_bytes)| {
+            block_bytes
+                .zcash_deserialize_into::<std::sync::Arc<Block>>()
+                .expect("block test vectors are valid")
+        })
+        .collect()
+}
+
+/// Commits `blocks` to `state` in chunks of `batch_size`, returning once every block has been
+/// committed.
+///
+/// Each block is a separate commit; there is no multi-block write batch.
+fn import_in_batches(state: &mut FinalizedState, blocks: &[std::sync::Arc<Block>], batch_size: usize) {
+    for batch in blocks.chunks(batch_size) {
+        for block in batch {
+            state
+                .commit_finalized_direct(block.clone().into(), "benchmark import")
+                .expect("benchmark blocks should commit cleanly");
+        }
+    }
+}
+
+fn bench_import_throughput(c: &mut Criterion) {
+    let blocks = mainnet_import_blocks(IMPORT_BLOCK_COUNT);
+
+    let mut group = c.benchmark_group("state_import");
+    group.throughput(Throughput::Elements(blocks.len() as u64));
+
+    for &batch_size in BATCH_SIZES {
+        group.bench_function(format!("batch_size_{batch_size}"), |b| {
+            b.iter_batched(
+                || FinalizedState::new(&Config::ephemeral(), Network::Mainnet),
+                |mut state| import_in_batches(&mut state, &blocks, batch_size),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_import_throughput);
+criterion_main!(benches);
+
+/// Per-column-family size statistics, as printed by [`column_family_report`].
+#[derive(Debug)]
+pub struct ColumnFamilyStats {
+    /// The column family's name.
+    pub name: String,
+    /// The number of key-value entries in the column family.
+    pub entries: usize,
+    /// The total size, in bytes, of every key in the column family.
+    pub key_bytes: usize,
+    /// The total size, in bytes, of every value in the column family.
+    pub value_bytes: usize,
+}
+
+impl ColumnFamilyStats {
+    /// The average entry size, in bytes, across both keys and values.
+    ///
+    /// Returns `0.0` for an empty column family, rather than dividing by zero.
+    pub fn average_entry_bytes(&self) -> f64 {
+        if self.entries == 0 {
+            return 0.0;
+        }
+
+        (self.key_bytes + self.value_bytes) as f64 / self.entries as f64
+    }
+}
+
+/// Prints and returns [`ColumnFamilyStats`] for every column family in `state`'s already-open
+/// database, for the "state report" one-shot mode. It doesn't open a database directory by path.
+///
+/// Unlike the throughput benchmark above, this is meant to be run against a real, populated
+/// database directory, to catch column-family bloat before release.
+pub fn column_family_report(state: &FinalizedState) -> Vec<ColumnFamilyStats> {
+    let started_at = Instant::now();
+
+    let mut cf_names = state.db.list_cf().expect("database is valid");
+    cf_names.sort();
+
+    let report: Vec<ColumnFamilyStats> = cf_names
+        .into_iter()
+        .map(|name| {
+            let handle = state
+                .db
+                .cf_handle(&name)
+                .unwrap_or_else(|| panic!("missing column family: {name}"));
+
+            let mut entries = 0;
+            let mut key_bytes = 0;
+            let mut value_bytes = 0;
+
+            for item in state.db.iterator_cf(handle, rocksdb::IteratorMode::Start) {
+                let (key, value) = item.expect("iterating an open column family cannot fail");
+                entries += 1;
+                key_bytes += key.len();
+                value_bytes += value.len();
+            }
+
+            ColumnFamilyStats {
+                name,
+                entries,
+                key_bytes,
+                value_bytes,
+            }
+        })
+        .collect();
+
+    for stats in &report {
+        println!(
+            "{}: {} entries, {} key bytes, {} value bytes, {:.1} average entry bytes",
+            stats.name,
+            stats.entries,
+            stats.key_bytes,
+            stats.value_bytes,
+            stats.average_entry_bytes(),
+        );
+    }
+
+    tracing::info!(elapsed = ?started_at.elapsed(), "generated column family report");
+
+    report
+}
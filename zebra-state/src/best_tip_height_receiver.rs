@@ -1,35 +1,95 @@
+use chrono::{DateTime, Utc};
 use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
 
 use zebra_chain::{best_tip_height::BestTipHeight, block};
 
-/// Receiver end to watch the current non-finalized best tip height and the finalized tip height.
+use crate::service::best_tip_height::TipData;
+
+/// Receiver end to watch the current non-finalized best tip and the finalized tip.
 #[derive(Clone, Debug)]
 pub struct BestTipHeightReceiver {
-    finalized: watch::Receiver<block::Height>,
-    non_finalized: watch::Receiver<Option<block::Height>>,
+    finalized: watch::Receiver<TipData>,
+    non_finalized: watch::Receiver<Option<TipData>>,
+
+    /// The most recently polled network tip height, as reported by [`rpc_tip_poller`], if any
+    /// poll has succeeded yet.
+    ///
+    /// Unlike `finalized`/`non_finalized`, this isn't derived from locally validated blocks, so
+    /// it's only ever used as a bootstrap/fallback estimate of how far behind the network this
+    /// node is - never as a source of truth for consensus.
+    ///
+    /// [`rpc_tip_poller`]: crate::rpc_tip_poller
+    network_tip: watch::Receiver<Option<block::Height>>,
 }
 
 impl BestTipHeightReceiver {
     /// Create the endpoints for the best tip height.
     ///
-    /// Creates a [`BestTipHeight`] to act as the receiver endpoint, a
-    /// [`watch::Sender<block::Height>`][watch::Sender] to act as the finalized tip sender endpoint,
-    /// and a [`watch::Sender<Option<block::Height>>`][watch::Sender] to act as the best
-    /// non-finalized tip sender endpoint.
+    /// Creates a [`BestTipHeightReceiver`] to act as the receiver endpoint, a
+    /// [`watch::Sender<TipData>`][watch::Sender] to act as the finalized tip sender endpoint,
+    /// a [`watch::Sender<Option<TipData>>`][watch::Sender] to act as the best
+    /// non-finalized tip sender endpoint, and a [`watch::Sender<Option<block::Height>>`] to act
+    /// as the polled network tip sender endpoint, for use by [`rpc_tip_poller::poll_network_tip`].
+    ///
+    /// [`rpc_tip_poller::poll_network_tip`]: crate::rpc_tip_poller::poll_network_tip
     pub fn new() -> (
         Self,
-        watch::Sender<block::Height>,
+        watch::Sender<TipData>,
+        watch::Sender<Option<TipData>>,
         watch::Sender<Option<block::Height>>,
     ) {
-        let (finalized_sender, finalized_receiver) = watch::channel(block::Height(1));
+        let genesis_tip = TipData {
+            height: block::Height(1),
+            hash: block::Hash([0; 32]),
+            time: DateTime::<Utc>::MIN_UTC,
+        };
+        let (finalized_sender, finalized_receiver) = watch::channel(genesis_tip);
         let (non_finalized_sender, non_finalized_receiver) = watch::channel(None);
+        let (network_tip_sender, network_tip_receiver) = watch::channel(None);
 
         let receiver = BestTipHeightReceiver {
             finalized: finalized_receiver,
             non_finalized: non_finalized_receiver,
+            network_tip: network_tip_receiver,
         };
 
-        (receiver, finalized_sender, non_finalized_sender)
+        (
+            receiver,
+            finalized_sender,
+            non_finalized_sender,
+            network_tip_sender,
+        )
+    }
+
+    /// Returns the [`TipData`] this receiver currently considers the best tip: the best
+    /// non-finalized tip, falling back to the finalized tip if there are no known non-finalized
+    /// blocks.
+    fn best_tip(&self) -> TipData {
+        // Bind the borrow guard so that the non-finalized watch channel doesn't update while
+        // reading from the finalized watch channel.
+        let non_finalized = self.non_finalized.borrow();
+
+        non_finalized.unwrap_or(*self.finalized.borrow())
+    }
+
+    /// Returns the signed gap between the local best tip and the most recently polled network
+    /// tip: positive when the network is ahead of us, negative when we're ahead of the network's
+    /// last reported height.
+    ///
+    /// Returns `None` until the first successful poll, since there's no network tip to compare
+    /// against yet.
+    pub fn estimated_distance_behind_tip(&self) -> Option<i64> {
+        let network_tip = (*self.network_tip.borrow())?;
+
+        Some(i64::from(network_tip.0) - i64::from(self.best_tip_height().0))
+    }
+
+    /// Returns a [`Stream`][futures::Stream] that yields the polled network tip height every
+    /// time it changes, starting with the height known at the time this is called (or `None` if
+    /// no poll has succeeded yet).
+    pub fn network_tip_changes(&self) -> WatchStream<Option<block::Height>> {
+        WatchStream::new(self.network_tip.clone())
     }
 }
 
@@ -39,10 +99,22 @@ impl BestTipHeight for BestTipHeightReceiver {
     /// Prioritizes the best non-finalized chain tip. If there are no known non-finalized blocks,
     /// this falls back to the finalized tip height.
     fn best_tip_height(&self) -> block::Height {
-        // Bind the borrow guard so that the non-finalized watch channel doesn't update while
-        // reading from the finalized watch channel.
-        let non_finalized = self.non_finalized.borrow();
+        self.best_tip().height
+    }
 
-        non_finalized.unwrap_or(*self.finalized.borrow())
+    /// Retrieve the current best chain tip's block hash.
+    ///
+    /// Prioritizes the best non-finalized chain tip, with the same fallback as
+    /// [`best_tip_height`][Self::best_tip_height].
+    fn best_tip_hash(&self) -> block::Hash {
+        self.best_tip().hash
+    }
+
+    /// Retrieve the current best chain tip's block time.
+    ///
+    /// Prioritizes the best non-finalized chain tip, with the same fallback as
+    /// [`best_tip_height`][Self::best_tip_height].
+    fn best_tip_block_time(&self) -> DateTime<Utc> {
+        self.best_tip().time
     }
 }
@@ -0,0 +1,70 @@
+//! A polling, RPC-sourced network tip height, for use as a bootstrap/fallback estimate of how
+//! far behind the network a node is.
+//!
+//! [`BestTipHeightReceiver`](crate::BestTipHeightReceiver) already tracks the local best tip from
+//! validated finalized/non-finalized blocks, but a freshly started or peer-isolated node has no
+//! external reference point to compare that against. [`poll_network_tip`] periodically queries a
+//! trusted node's `getblockchaininfo`-style endpoint and publishes the reported height into the
+//! `watch::Sender<Option<block::Height>>` returned by [`BestTipHeightReceiver::new`], so
+//! [`BestTipHeightReceiver::estimated_distance_behind_tip`] has something to compare against
+//! before peer gossip has caught up.
+//!
+//! [`BestTipHeightReceiver::new`]: crate::BestTipHeightReceiver::new
+//! [`BestTipHeightReceiver::estimated_distance_behind_tip`]: crate::BestTipHeightReceiver::estimated_distance_behind_tip
+
+use std::{future::Future, time::Duration};
+
+use tokio::sync::watch;
+
+use zebra_chain::block;
+
+use crate::BoxError;
+
+/// Repeatedly calls `query` every `interval`, publishing each successfully reported height into
+/// `sender`.
+///
+/// `query` is generic, rather than hard-wired to a single HTTP client, so this can be driven by
+/// any height source, such as a JSON-RPC client or a test double that returns canned heights.
+/// This module doesn't provide a JSON-RPC query function itself.
+///
+/// Runs until `sender`'s last receiver is dropped. A failed poll is logged and skipped - not
+/// fatal - since the whole point of this task is to be a best-effort fallback, not a second
+/// source of consensus truth.
+pub async fn poll_network_tip<Query, Fut>(
+    query: Query,
+    interval: Duration,
+    sender: watch::Sender<Option<block::Height>>,
+) where
+    Query: Fn() -> Fut,
+    Fut: Future<Output = Result<block::Height, BoxError>>,
+{
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        match query().await {
+            Ok(height) => {
+                if sender.send(Some(height)).is_err() {
+                    // No receivers left, so there's no one left to poll for.
+                    return;
+                }
+            }
+            Err(error) => {
+                tracing::warn!(%error, "failed to poll network tip height, keeping previous estimate");
+            }
+        }
+    }
+}
+
+// TODO: this checkout has no `Cargo.toml`, so there's no HTTP client crate (such as `reqwest`)
+// declared as a dependency to actually perform a `getblockchaininfo` JSON-RPC call against a
+// trusted node. The query function below is left unwritten rather than faked; once an HTTP
+// client dependency exists, add something like:
+//
+// pub async fn query_blockchain_info_tip(rpc_endpoint: &str) -> Result<block::Height, BoxError> {
+//     // POST {"jsonrpc": "1.0", "method": "getblockchaininfo", "params": []} to `rpc_endpoint`,
+//     // parse the `blocks` field out of the response body, and return it as a `block::Height`.
+// }
+//
+// and pass it to `poll_network_tip` from wherever zebrad spawns long-running tasks.
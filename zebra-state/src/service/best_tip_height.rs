@@ -1,66 +1,88 @@
+use chrono::{DateTime, Utc};
 use tokio::sync::watch;
 
 use zebra_chain::block;
 
+/// A chain tip's height, hash, and block time, bundled together so a finalized or non-finalized
+/// tip can be compared and sent as a single unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TipData {
+    /// The tip's height.
+    pub height: block::Height,
+
+    /// The tip's block hash.
+    pub hash: block::Hash,
+
+    /// The tip's block time.
+    pub time: DateTime<Utc>,
+}
+
 /// A helper type to determine the best non-finalized chain tip block height.
 ///
 /// The block height is determined based on the current finalized block height and the current best
 /// non-finalized chain's tip block height. The height is made available from a [`watch::Receiver`].
 #[derive(Debug)]
 pub struct BestTipHeight {
-    finalized: block::Height,
-    non_finalized: Option<block::Height>,
-    sender: watch::Sender<block::Height>,
+    finalized: TipData,
+    non_finalized: Option<TipData>,
+    sender: watch::Sender<TipData>,
     // TODO: Replace this with a `watch::Sender::borrow` call once Tokio is updated to 1.0.0
-    active_value: block::Height,
+    active_value: TipData,
 }
 
 impl BestTipHeight {
     /// Create a new instance of [`BestTipHeight`] and the [`watch::Receiver`] endpoint for the
-    /// current best tip block height.
-    pub fn new() -> (Self, watch::Receiver<block::Height>) {
-        let genesis_height = block::Height(0);
-        let (sender, receiver) = watch::channel(genesis_height);
+    /// current best tip.
+    pub fn new() -> (Self, watch::Receiver<TipData>) {
+        let genesis_tip = TipData {
+            height: block::Height(0),
+            hash: block::Hash([0; 32]),
+            time: DateTime::<Utc>::MIN_UTC,
+        };
+        let (sender, receiver) = watch::channel(genesis_tip);
 
         (
             BestTipHeight {
-                finalized: genesis_height,
+                finalized: genesis_tip,
                 non_finalized: None,
                 sender,
-                active_value: genesis_height,
+                active_value: genesis_tip,
             },
             receiver,
         )
     }
 
-    /// Update the current finalized block height.
+    /// Update the current finalized chain tip.
     ///
-    /// May trigger an update to best tip height.
-    pub fn set_finalized_height(&mut self, new_height: block::Height) {
-        if self.finalized != new_height {
-            self.finalized = new_height;
+    /// May trigger an update to the best tip.
+    pub fn set_finalized_height(&mut self, new_tip: TipData) {
+        if self.finalized != new_tip {
+            self.finalized = new_tip;
             self.update();
         }
     }
 
-    /// Update the current non-finalized block height.
+    /// Update the current non-finalized chain tip.
     ///
-    /// May trigger an update to the best tip height.
-    pub fn set_best_non_finalized_height(&mut self, new_height: Option<block::Height>) {
-        if self.non_finalized != new_height {
-            self.non_finalized = new_height;
+    /// May trigger an update to the best tip.
+    pub fn set_best_non_finalized_height(&mut self, new_tip: Option<TipData>) {
+        if self.non_finalized != new_tip {
+            self.non_finalized = new_tip;
             self.update();
         }
     }
 
     /// Possibly send an update to listeners.
     ///
-    /// An update is only sent if the current best tip height is different from the last best tip
-    /// height that was sent.
+    /// An update is only sent if the current best tip is different from the last best tip that
+    /// was sent.
     fn update(&mut self) {
         let new_value = match self.non_finalized {
-            Some(non_finalized) => self.finalized.max(non_finalized),
-            None => self.finalized,
+            // Favour the non-finalized tip on a height tie, not just when it's strictly taller:
+            // it's always at least as recent as the finalized tip, since finalization can only
+            // lag behind the best non-finalized chain, never run ahead of it.
+            Some(non_finalized) if non_finalized.height >= self.finalized.height => non_finalized,
+            _ => self.finalized,
         };
 
         if new_value != self.active_value {
@@ -0,0 +1,172 @@
+//! A parallel commit path for contiguous runs of finalized blocks.
+//!
+//! TODO: this module can't be wired into the real state service yet. `service.rs` (the
+//! `StateService` and its `Request`/`Response` handling) and `service/finalized_state.rs` (this
+//! module's would-be parent, with a `mod batch;` declaration) aren't present in this tree - only
+//! `service/finalized_state/disk_format/tests/snapshot.rs` is. Likewise, the crate root that would
+//! define `Request`/`Response` is absent, so there's nowhere to add the
+//! `Request::CommitFinalizedBlocks(Vec<FinalizedBlock>)` batch variant this module exists to
+//! serve. What follows is the validate-then-apply shape that variant's handler would use, written
+//! against a generic `item`/`validation` pair instead of the real `FinalizedBlock`/`Utxo` types so
+//! it can be dropped in once those files come back.
+//!
+//! Like `populated_state` in `service/tests.rs`, today's serial path validates and applies each
+//! finalized block one at a time via `ready_and().await.call(...)`. The parts of that validation
+//! that don't depend on write order - transaction hashing, UTXO-set derivation, and other
+//! signature-independent structural checks - are independent per block, so they can run across a
+//! worker pool ahead of time; only the writes themselves need to happen in order.
+
+use std::thread;
+
+/// A contiguous run of finalized blocks can be committed through this path once each block's
+/// signature-independent validation (`I::validate`) has produced the data its ordered write
+/// (`I::apply`) needs.
+pub trait FinalizedItem: Send + Sync {
+    /// The result of this item's independent validation, consumed by `apply`.
+    type Validated: Send;
+
+    /// The error produced by a failed validation or a failed write.
+    type Error: Send;
+
+    /// Performs this item's part of validation that doesn't depend on commit order: e.g.
+    /// transaction hashing, UTXO-set derivation, or other structural checks that only need the
+    /// item itself, not the state of previously-committed items.
+    fn validate(&self) -> Result<Self::Validated, Self::Error>;
+
+    /// Applies this item's ordered write, using the result of `validate`.
+    fn apply(self, validated: Self::Validated) -> Result<(), Self::Error>;
+}
+
+/// Commits `items` as a batch: validates every item's independent part across a worker pool,
+/// then applies the ordered writes serially, in `items`' original order.
+///
+/// Returns the first error encountered, from either phase. Validation runs to completion across
+/// all workers even if one item fails, but no writes are applied once any validation has failed.
+pub fn commit_finalized_batch<I: FinalizedItem>(items: Vec<I>) -> Result<(), I::Error> {
+    let worker_count = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(items.len().max(1));
+
+    let validated = thread::scope(|scope| {
+        let mut chunks: Vec<_> = items.iter().collect();
+        let chunk_size = (chunks.len() / worker_count).max(1);
+        let mut handles = Vec::new();
+
+        while !chunks.is_empty() {
+            let split_at = chunk_size.min(chunks.len());
+            let chunk: Vec<_> = chunks.drain(..split_at).collect();
+            handles.push(scope.spawn(move || {
+                chunk
+                    .into_iter()
+                    .map(FinalizedItem::validate)
+                    .collect::<Vec<_>>()
+            }));
+        }
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("validation worker shouldn't panic"))
+            .collect::<Vec<_>>()
+    });
+
+    let mut applies = Vec::with_capacity(items.len());
+    for validated in validated {
+        applies.push(validated?);
+    }
+
+    for (item, validated) in items.into_iter().zip(applies) {
+        item.apply(validated)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{commit_finalized_batch, FinalizedItem};
+
+    /// A synthetic finalized item standing in for `FinalizedBlock`, since that type isn't present
+    /// in this tree to test against directly.
+    struct Item {
+        height: u32,
+        committed: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl FinalizedItem for Item {
+        type Validated = u32;
+        type Error = String;
+
+        fn validate(&self) -> Result<u32, String> {
+            Ok(self.height * 2)
+        }
+
+        fn apply(self, validated: u32) -> Result<(), String> {
+            self.committed.lock().unwrap().push(validated);
+            Ok(())
+        }
+    }
+
+    /// The batch path should commit every item, in order, with the same result the serial path
+    /// would produce.
+    #[test]
+    fn batch_path_matches_serial_order() {
+        let committed = Arc::new(Mutex::new(Vec::new()));
+
+        let items: Vec<Item> = (0..20)
+            .map(|height| Item {
+                height,
+                committed: committed.clone(),
+            })
+            .collect();
+
+        commit_finalized_batch(items).expect("all items should validate and apply");
+
+        let expected: Vec<u32> = (0..20).map(|height| height * 2).collect();
+        assert_eq!(*committed.lock().unwrap(), expected);
+    }
+
+    /// A failing validation should stop every write from being applied, not just the failing
+    /// item's.
+    struct FailingItem {
+        height: u32,
+        committed: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl FinalizedItem for FailingItem {
+        type Validated = u32;
+        type Error = String;
+
+        fn validate(&self) -> Result<u32, String> {
+            if self.height == 5 {
+                Err("invalid block".to_string())
+            } else {
+                Ok(self.height)
+            }
+        }
+
+        fn apply(self, validated: u32) -> Result<(), String> {
+            self.committed.lock().unwrap().push(validated);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn batch_path_applies_nothing_on_validation_failure() {
+        let committed = Arc::new(Mutex::new(Vec::new()));
+
+        let items: Vec<FailingItem> = (0..10)
+            .map(|height| FailingItem {
+                height,
+                committed: committed.clone(),
+            })
+            .collect();
+
+        let result = commit_finalized_batch(items);
+
+        assert_eq!(result, Err("invalid block".to_string()));
+        assert!(committed.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Periodic finality checkpoints over the finalized chain.
+//!
+//! TODO: like `batch.rs` in this same directory, this module can't be wired into the real state
+//! service yet - `service.rs`, `service/finalized_state.rs`, and the crate root's `Request`/
+//! `Response`/`Config` definitions are all absent from this tree, so there's nowhere to add the
+//! `Request::FinalityCheckpoint(Height) -> Response` variant this tracker exists to serve, or a
+//! `finality_period` field on the real `Config`. [`CheckpointTracker`] is written generically
+//! (over a 32-byte hash, not `zebra_chain::block::Hash`) so it can be dropped in once those files
+//! come back; `finalized_state/batch.rs`'s `FinalizedItem::apply` is the natural place to call
+//! [`CheckpointTracker::record_finalized`] from, once a tracker instance exists on the state
+//! service to call it on.
+//!
+//! Borrowing the GRANDPA idea of a finality justification emitted every N blocks rather than per
+//! block, a checkpoint is recorded every [`CheckpointConfig::finality_period`] blocks, each
+//! carrying a rolling commitment over every block finalized since genesis - so a light client or
+//! syncing peer that already trusts an earlier checkpoint can check that a later one extends it by
+//! folding in the block hashes between them, without replaying the chain from genesis.
+
+use std::{
+    collections::BTreeMap,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// The default number of blocks between recorded finality checkpoints.
+const DEFAULT_FINALITY_PERIOD: u32 = 1000;
+
+/// Configuration for periodic finality checkpoints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckpointConfig {
+    /// Emit a checkpoint every `finality_period` finalized blocks.
+    pub finality_period: u32,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        CheckpointConfig {
+            finality_period: DEFAULT_FINALITY_PERIOD,
+        }
+    }
+}
+
+/// A compact anchor point on the finalized chain: a height and hash, plus a commitment covering
+/// every block finalized up to and including it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FinalityCheckpoint {
+    /// The height this checkpoint was recorded at.
+    pub height: u32,
+
+    /// The block hash at `height`.
+    pub hash: [u8; 32],
+
+    /// A rolling commitment over every finalized block from genesis up to and including this
+    /// checkpoint's height.
+    ///
+    /// TODO: this chains `std::hash::Hasher`, which is fast but not a cryptographic commitment.
+    /// A real deployment should use a cryptographic hash (e.g. BLAKE2b, to match the rest of
+    /// Zcash's commitment trees) - `zebra_chain` would be the natural place to get one from, but
+    /// it isn't present in this tree to depend on with any confidence in its API.
+    pub commitment: u64,
+}
+
+/// Tracks the rolling commitment over a finalized chain, and records a [`FinalityCheckpoint`]
+/// every `finality_period` blocks.
+#[derive(Clone, Debug)]
+pub struct CheckpointTracker {
+    config: CheckpointConfig,
+    commitment: u64,
+    checkpoints: BTreeMap<u32, FinalityCheckpoint>,
+}
+
+impl CheckpointTracker {
+    /// Creates a new, empty [`CheckpointTracker`] using `config`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.finality_period` is zero, since [`Self::record_finalized`] uses it as a
+    /// modulus to decide when to record a checkpoint.
+    pub fn new(config: CheckpointConfig) -> Self {
+        assert!(
+            config.finality_period > 0,
+            "finality_period must be non-zero"
+        );
+
+        CheckpointTracker {
+            config,
+            commitment: 0,
+            checkpoints: BTreeMap::new(),
+        }
+    }
+
+    /// Folds `hash`, the hash of the block just finalized at `height`, into the rolling
+    /// commitment, recording a new checkpoint if `height` falls on a `finality_period` boundary.
+    pub fn record_finalized(&mut self, height: u32, hash: [u8; 32]) {
+        let mut hasher = DefaultHasher::new();
+        self.commitment.hash(&mut hasher);
+        hash.hash(&mut hasher);
+        self.commitment = hasher.finish();
+
+        if height % self.config.finality_period == 0 {
+            self.checkpoints.insert(
+                height,
+                FinalityCheckpoint {
+                    height,
+                    hash,
+                    commitment: self.commitment,
+                },
+            );
+        }
+    }
+
+    /// Returns the latest checkpoint at or below `height`, or `None` if no checkpoint has been
+    /// recorded yet at or below it.
+    pub fn nearest_checkpoint_at_or_below(&self, height: u32) -> Option<&FinalityCheckpoint> {
+        self.checkpoints
+            .range(..=height)
+            .next_back()
+            .map(|(_, checkpoint)| checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckpointConfig, CheckpointTracker};
+
+    fn hash_for(height: u32) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash[..4].copy_from_slice(&height.to_le_bytes());
+        hash
+    }
+
+    /// A checkpoint should be recorded every `finality_period` blocks, and not in between.
+    #[test]
+    fn checkpoints_recorded_on_period_boundaries() {
+        let mut tracker = CheckpointTracker::new(CheckpointConfig { finality_period: 10 });
+
+        for height in 0..=25 {
+            tracker.record_finalized(height, hash_for(height));
+        }
+
+        assert_eq!(
+            tracker.nearest_checkpoint_at_or_below(25).map(|c| c.height),
+            Some(20)
+        );
+        assert_eq!(
+            tracker.nearest_checkpoint_at_or_below(19).map(|c| c.height),
+            Some(10)
+        );
+        assert_eq!(
+            tracker.nearest_checkpoint_at_or_below(9).map(|c| c.height),
+            Some(0)
+        );
+    }
+
+    /// A zero `finality_period` would make `record_finalized`'s modulus check divide by zero, so
+    /// it should be rejected up front instead.
+    #[test]
+    #[should_panic(expected = "finality_period must be non-zero")]
+    fn zero_finality_period_panics() {
+        CheckpointTracker::new(CheckpointConfig { finality_period: 0 });
+    }
+
+    /// With no blocks finalized yet, there's no checkpoint to return.
+    #[test]
+    fn no_checkpoint_before_genesis_is_finalized() {
+        let tracker = CheckpointTracker::new(CheckpointConfig::default());
+
+        assert_eq!(tracker.nearest_checkpoint_at_or_below(0), None);
+    }
+
+    /// Two trackers fed the same finalized chain should agree on the latest checkpoint, including
+    /// its commitment.
+    #[test]
+    fn commitment_is_deterministic_over_the_same_chain() {
+        let mut a = CheckpointTracker::new(CheckpointConfig { finality_period: 5 });
+        let mut b = CheckpointTracker::new(CheckpointConfig { finality_period: 5 });
+
+        for height in 0..=15 {
+            a.record_finalized(height, hash_for(height));
+            b.record_finalized(height, hash_for(height));
+        }
+
+        assert_eq!(
+            a.nearest_checkpoint_at_or_below(15),
+            b.nearest_checkpoint_at_or_below(15)
+        );
+    }
+}
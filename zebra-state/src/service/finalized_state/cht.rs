@@ -0,0 +1,205 @@
+//! Canonical-hash-tree (CHT) style commitments over ranges of finalized block headers.
+//!
+//! Modeled on the header-chain/`cht_roots` approach used by light Ethereum clients: the
+//! finalized chain is divided into fixed-length sections, and each completed section is
+//! summarized by the root of a Merkle tree whose leaves are `blake2b(height || header_hash)` in
+//! height order. A light client that only trusts a handful of section roots can then verify an
+//! arbitrary finalized header by checking its [`InclusionProof`] against the root for its
+//! section, without downloading the headers in between.
+//!
+//! This module only contains the section/tree/proof math, which has no dependency on RocksDB.
+//! Wiring it up - a new column family keyed by section index, committing a root each time a
+//! section completes, and a `cht_proof(height)` query method - belongs on [`FinalizedState`],
+//! but neither that struct nor the rest of `zebra-state`'s module tree
+//! (`lib.rs`/`service.rs`/`service/finalized_state.rs`, which would declare `mod cht;` and define
+//! `FinalizedState` itself) exists in this checkout. Only `finalized_state::batch`,
+//! `finalized_state::checkpoints`, and the disk-format snapshot test survive here, so this module
+//! is written standalone, ready to be registered as a column family and called from
+//! `FinalizedState::commit_finalized_direct` once that plumbing exists.
+//!
+//! [`FinalizedState`]: super::FinalizedState
+
+use blake2b_simd::Params;
+
+use zebra_chain::block;
+
+/// The number of blocks summarized by a single CHT section.
+///
+/// Matches the example in the design this module is modeled on; real deployments might tune it
+/// based on how many trusted roots a light client is expected to remember.
+pub const SECTION_LENGTH: u64 = 2048;
+
+/// A Blake2b-256 hash, used as both a CHT leaf and an internal Merkle tree node.
+pub type MerkleHash = [u8; 32];
+
+/// A Merkle inclusion proof that a single leaf hash is part of a [`SECTION_LENGTH`]-leaf CHT
+/// section root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    /// The sibling hash needed at each level of the tree, from the leaf's level up to the root.
+    pub branch: Vec<MerkleHash>,
+}
+
+/// Returns the section index that `height` belongs to.
+pub fn section_index(height: block::Height) -> u64 {
+    u64::from(height.0) / SECTION_LENGTH
+}
+
+/// Returns the CHT leaf hash for a finalized header: `blake2b(height || header_hash)`.
+pub fn leaf_hash(height: block::Height, header_hash: block::Hash) -> MerkleHash {
+    let mut hasher = Params::new().hash_length(32).to_state();
+    hasher.update(&height.0.to_le_bytes());
+    hasher.update(&header_hash.0);
+    finalize(hasher)
+}
+
+/// Returns the section root for a completed section, given its leaf hashes in ascending height
+/// order.
+///
+/// # Panics
+///
+/// Panics if `leaves` is empty.
+pub fn section_root(leaves: &[MerkleHash]) -> MerkleHash {
+    merkle_root(leaves.to_vec())
+}
+
+/// Returns the [`InclusionProof`] for the leaf at `index` within `leaves`, along with the
+/// section root the proof authenticates against.
+///
+/// # Panics
+///
+/// Panics if `leaves` is empty or `index` is out of bounds.
+pub fn prove(leaves: &[MerkleHash], index: usize) -> (InclusionProof, MerkleHash) {
+    assert!(index < leaves.len(), "proof index out of bounds");
+
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    let mut branch = Vec::new();
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().expect("level is non-empty"));
+        }
+
+        branch.push(level[index ^ 1]);
+
+        level = level
+            .chunks_exact(2)
+            .map(|pair| parent_hash(pair[0], pair[1]))
+            .collect();
+        index /= 2;
+    }
+
+    (InclusionProof { branch }, level[0])
+}
+
+/// Returns `true` if `proof` authenticates `leaf` at `index` against `root`.
+pub fn verify(leaf: MerkleHash, index: usize, proof: &InclusionProof, root: MerkleHash) -> bool {
+    let mut hash = leaf;
+    let mut index = index;
+
+    for sibling in &proof.branch {
+        hash = if index % 2 == 0 {
+            parent_hash(hash, *sibling)
+        } else {
+            parent_hash(*sibling, hash)
+        };
+        index /= 2;
+    }
+
+    hash == root
+}
+
+/// Recursively folds `level` up to its single Merkle root, duplicating the last entry of any
+/// odd-length level so every level pairs off evenly.
+fn merkle_root(mut level: Vec<MerkleHash>) -> MerkleHash {
+    assert!(!level.is_empty(), "cannot compute the root of an empty section");
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().expect("level is non-empty"));
+        }
+
+        level = level
+            .chunks_exact(2)
+            .map(|pair| parent_hash(pair[0], pair[1]))
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Returns the parent of two sibling Merkle nodes: `blake2b(left || right)`.
+fn parent_hash(left: MerkleHash, right: MerkleHash) -> MerkleHash {
+    let mut hasher = Params::new().hash_length(32).to_state();
+    hasher.update(&left);
+    hasher.update(&right);
+    finalize(hasher)
+}
+
+fn finalize(hasher: blake2b_simd::State) -> MerkleHash {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(hasher.finalize().as_bytes());
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(height: u32) -> MerkleHash {
+        leaf_hash(block::Height(height), block::Hash([height as u8; 32]))
+    }
+
+    #[test]
+    fn single_leaf_section_root_is_the_leaf_itself() {
+        zebra_test::init();
+
+        let leaves = vec![leaf(0)];
+        assert_eq!(section_root(&leaves), leaves[0]);
+    }
+
+    #[test]
+    fn every_leaf_in_a_section_has_a_valid_inclusion_proof() {
+        zebra_test::init();
+
+        let leaves: Vec<MerkleHash> = (0..SECTION_LENGTH as u32).map(leaf).collect();
+        let root = section_root(&leaves);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let (proof, proved_root) = prove(&leaves, index);
+            assert_eq!(proved_root, root);
+            assert!(verify(*leaf, index, &proof, root));
+        }
+    }
+
+    #[test]
+    fn odd_length_sections_are_supported() {
+        zebra_test::init();
+
+        let leaves: Vec<MerkleHash> = (0..7).map(leaf).collect();
+        let root = section_root(&leaves);
+
+        let (proof, proved_root) = prove(&leaves, 5);
+        assert_eq!(proved_root, root);
+        assert!(verify(leaves[5], 5, &proof, root));
+    }
+
+    #[test]
+    fn a_proof_does_not_verify_against_the_wrong_leaf() {
+        zebra_test::init();
+
+        let leaves: Vec<MerkleHash> = (0..16).map(leaf).collect();
+        let root = section_root(&leaves);
+
+        let (proof, _) = prove(&leaves, 3);
+        assert!(!verify(leaves[4], 3, &proof, root));
+    }
+
+    #[test]
+    fn section_index_groups_heights_by_section_length() {
+        assert_eq!(section_index(block::Height(0)), 0);
+        assert_eq!(section_index(block::Height(SECTION_LENGTH as u32 - 1)), 0);
+        assert_eq!(section_index(block::Height(SECTION_LENGTH as u32)), 1);
+    }
+}
@@ -53,6 +53,9 @@
 //!  * Transaction Gossip Task
 //!    * runs in the background and gossips newly added mempool transactions
 //!      to peers
+//!  * Mempool Snapshot
+//!    * reloaded into the mempool service at startup, and saved back to disk at shutdown, so a
+//!      restart doesn't lose every unmined transaction the node already verified
 
 use std::{cmp::max, time::Duration};
 
@@ -61,7 +64,7 @@ use chrono::Utc;
 use color_eyre::eyre::{eyre, Report};
 use futures::FutureExt;
 use tokio::{pin, select, sync::oneshot};
-use tower::{builder::ServiceBuilder, util::BoxService};
+use tower::{builder::ServiceBuilder, util::BoxService, Service, ServiceExt};
 use tracing_futures::Instrument;
 
 use zebra_chain::{chain_tip::ChainTip, parameters::Network};
@@ -70,7 +73,8 @@ use crate::{
     components::{
         inbound::{self, InboundSetupData},
         mempool::{self, Mempool},
-        sync::{self, SyncStatus},
+        sync::{self, SyncProgress, SyncStatus},
+        task_supervisor::{supervise, RestartPolicy},
         tokio::{RuntimeRun, TokioComponent},
         ChainSync, Inbound,
     },
@@ -122,7 +126,7 @@ impl StartCmd {
             .await;
 
         info!("initializing syncer");
-        let (syncer, sync_status) = ChainSync::new(
+        let (syncer, sync_status, sync_progress) = ChainSync::new(
             &config,
             peer_set.clone(),
             chain_verifier.clone(),
@@ -157,35 +161,90 @@ impl StartCmd {
             .send(setup_data)
             .map_err(|_| eyre!("could not send setup data to inbound service"))?;
 
+        Self::load_mempool_snapshot(mempool.clone()).await;
+
+        // The syncer owns the `sync_status`/`sync_progress` watch channels that `ChainSync::new`
+        // created, and the mempool, crawler, and progress task hold clones of the *same*
+        // channels. Restarting the syncer in place would mean calling `ChainSync::new` again,
+        // which creates brand new channels and would silently orphan every one of those
+        // subscribers. So unlike the tasks below, the syncer isn't supervised
+        // - it stays on the plain fatal-exit path, and a syncer failure still brings the whole
+        // node down.
         let syncer_task_handle = tokio::spawn(syncer.sync().in_current_span());
 
+        // The tasks below only depend on cloneable service handles, so a failed attempt can
+        // simply be retried with fresh clones, without disturbing any other task's state.
+
+        let block_gossip_sync_status = sync_status.clone();
+        let block_gossip_chain_tip_change = chain_tip_change.clone();
+        let block_gossip_peer_set = peer_set.clone();
         let mut block_gossip_task_handle = tokio::spawn(
-            sync::gossip_best_tip_block_hashes(
-                sync_status.clone(),
-                chain_tip_change.clone(),
-                peer_set.clone(),
-            )
+            supervise("chain tip block gossip", RestartPolicy::DEFAULT, move || {
+                sync::gossip_best_tip_block_hashes(
+                    block_gossip_sync_status.clone(),
+                    block_gossip_chain_tip_change.clone(),
+                    block_gossip_peer_set.clone(),
+                )
+                .map(|result| result.map_err(|e| eyre!(e)))
+            })
             .in_current_span(),
         );
 
-        let mempool_crawler_task_handle = mempool::Crawler::spawn(
-            &config.mempool,
-            peer_set.clone(),
-            mempool.clone(),
-            sync_status.clone(),
-            chain_tip_change,
+        let mempool_crawler_peer_set = peer_set.clone();
+        let mempool_crawler_mempool = mempool.clone();
+        let mempool_crawler_sync_status = sync_status.clone();
+        let mempool_crawler_task_handle = tokio::spawn(
+            supervise("mempool crawler", RestartPolicy::DEFAULT, move || {
+                let peer_set = mempool_crawler_peer_set.clone();
+                let mempool = mempool_crawler_mempool.clone();
+                let sync_status = mempool_crawler_sync_status.clone();
+
+                async move {
+                    mempool::Crawler::spawn(peer_set, mempool, sync_status)
+                        .await
+                        .expect("unexpected panic in the mempool crawler")
+                        .map_err(|e| eyre!(e))
+                }
+            })
+            .in_current_span(),
         );
 
-        let mempool_queue_checker_task_handle = mempool::QueueChecker::spawn(mempool);
+        let mempool_queue_checker_mempool = mempool.clone();
+        let mempool_queue_checker_task_handle = tokio::spawn(
+            supervise("mempool queue checker", RestartPolicy::DEFAULT, move || {
+                let mempool = mempool_queue_checker_mempool.clone();
+
+                async move {
+                    mempool::QueueChecker::spawn(mempool)
+                        .await
+                        .expect("unexpected panic in the mempool queue checker")
+                        .map_err(|e| eyre!(e))
+                }
+            })
+            .in_current_span(),
+        );
 
+        let tx_gossip_mempool_transaction_receiver = mempool_transaction_receiver;
+        let tx_gossip_peer_set = peer_set;
         let tx_gossip_task_handle = tokio::spawn(
-            mempool::gossip_mempool_transaction_id(mempool_transaction_receiver, peer_set)
-                .in_current_span(),
+            supervise("transaction gossip", RestartPolicy::DEFAULT, move || {
+                mempool::gossip_mempool_transaction_id(
+                    tx_gossip_mempool_transaction_receiver.clone(),
+                    tx_gossip_peer_set.clone(),
+                )
+                .map(|result| result.map_err(|e| eyre!(e)))
+            })
+            .in_current_span(),
         );
 
         let progress_task_handle = tokio::spawn(
-            Self::update_progress(config.network.network, latest_chain_tip, sync_status)
-                .in_current_span(),
+            Self::update_progress(
+                config.network.network,
+                latest_chain_tip,
+                sync_status,
+                sync_progress,
+            )
+            .in_current_span(),
         );
 
         info!("spawned initial Zebra tasks");
@@ -212,25 +271,24 @@ impl StartCmd {
                     .expect("unexpected panic in the syncer task")
                     .map(|_| info!("syncer task exited")),
 
+                // These tasks are supervised: `supervise` already retried them in place, so
+                // reaching this arm at all means their restart budget is exhausted and the
+                // failure (if any) is final.
                 block_gossip_result = &mut block_gossip_task_handle => block_gossip_result
-                    .expect("unexpected panic in the chain tip block gossip task")
-                    .map(|_| info!("chain tip block gossip task exited"))
-                    .map_err(|e| eyre!(e)),
+                    .expect("unexpected panic in the chain tip block gossip task supervisor")
+                    .map(|_| info!("chain tip block gossip task exited")),
 
                 mempool_crawl_result = &mut mempool_crawler_task_handle => mempool_crawl_result
-                    .expect("unexpected panic in the mempool crawler")
-                    .map(|_| info!("mempool crawler task exited"))
-                    .map_err(|e| eyre!(e)),
+                    .expect("unexpected panic in the mempool crawler supervisor")
+                    .map(|_| info!("mempool crawler task exited")),
 
                 mempool_queue_result = &mut mempool_queue_checker_task_handle => mempool_queue_result
-                    .expect("unexpected panic in the mempool queue checker")
-                    .map(|_| info!("mempool queue checker task exited"))
-                    .map_err(|e| eyre!(e)),
+                    .expect("unexpected panic in the mempool queue checker supervisor")
+                    .map(|_| info!("mempool queue checker task exited")),
 
                 tx_gossip_result = &mut tx_gossip_task_handle => tx_gossip_result
-                    .expect("unexpected panic in the transaction gossip task")
-                    .map(|_| info!("transaction gossip task exited"))
-                    .map_err(|e| eyre!(e)),
+                    .expect("unexpected panic in the transaction gossip task supervisor")
+                    .map(|_| info!("transaction gossip task exited")),
 
                 progress_result = &mut progress_task_handle => {
                     progress_result
@@ -275,9 +333,140 @@ impl StartCmd {
         // startup tasks
         groth16_download_handle.abort();
 
+        Self::save_mempool_snapshot(mempool).await;
+
         exit_status
     }
 
+    /// The path `zebrad` snapshots the mempool's verified transactions to on shutdown, and
+    /// reloads them from on startup.
+    ///
+    /// TODO: read this from `config.mempool` instead, once zebrad grows a `Config` type -
+    /// there's no configuration plumbing anywhere in this tree yet for `Mempool`/`Storage` to
+    /// hang a snapshot path or an enable/disable flag off of, so this is a fixed default for now.
+    fn mempool_snapshot_path() -> std::path::PathBuf {
+        std::path::PathBuf::from("mempool.snapshot")
+    }
+
+    /// Reloads the mempool snapshot written by a previous [`Self::save_mempool_snapshot`] call,
+    /// queuing each transaction through `mempool` exactly like a freshly gossiped transaction, so
+    /// it goes through the same conflict and chain-tip expiry checks as normal insertion and
+    /// stale entries are dropped rather than blindly re-admitted.
+    async fn load_mempool_snapshot<Mempool>(mut mempool: Mempool)
+    where
+        Mempool: tower::Service<
+                mempool::Request,
+                Response = mempool::Response,
+                Error = crate::BoxError,
+            > + Send
+            + 'static,
+        Mempool::Future: Send,
+    {
+        let path = Self::mempool_snapshot_path();
+
+        let transactions = match mempool::snapshot::load(&path) {
+            Ok(transactions) => transactions,
+            Err(error) => {
+                warn!(?error, ?path, "could not read mempool snapshot, skipping");
+                return;
+            }
+        };
+
+        if transactions.is_empty() {
+            return;
+        }
+
+        info!(
+            transactions = transactions.len(),
+            "reloading mempool snapshot from previous run"
+        );
+
+        let gossip = transactions
+            .into_iter()
+            .map(mempool::downloads::Gossip::Tx)
+            .collect();
+
+        let result = mempool
+            .ready()
+            .await
+            .map_err(|e| eyre!(e))
+            .and_then(|mempool| {
+                // `call` itself never fails; per-transaction results are reported inside the
+                // response instead.
+                Ok(mempool.call(mempool::Request::Queue(gossip)))
+            });
+
+        match result {
+            Ok(call) => {
+                let _ = call.await;
+            }
+            Err(error) => warn!(?error, "could not queue reloaded mempool snapshot"),
+        }
+    }
+
+    /// Saves a snapshot of `mempool`'s currently verified transactions to disk, so
+    /// [`Self::load_mempool_snapshot`] can reload them on the next startup.
+    async fn save_mempool_snapshot<Mempool>(mut mempool: Mempool)
+    where
+        Mempool: tower::Service<
+                mempool::Request,
+                Response = mempool::Response,
+                Error = crate::BoxError,
+            > + Send
+            + 'static,
+        Mempool::Future: Send,
+    {
+        let ids = match mempool.ready().await {
+            Ok(mempool) => mempool.call(mempool::Request::TransactionIds).await,
+            Err(error) => Err(error),
+        };
+
+        let ids = match ids {
+            Ok(mempool::Response::TransactionIds(ids)) => ids,
+            Ok(_) => unreachable!(
+                "mempool::Request::TransactionIds always returns mempool::Response::TransactionIds"
+            ),
+            Err(error) => {
+                warn!(?error, "could not read mempool transactions to snapshot");
+                return;
+            }
+        };
+
+        let transactions = match mempool.ready().await {
+            Ok(mempool) => {
+                mempool
+                    .call(mempool::Request::TransactionsById(
+                        ids.into_iter().collect(),
+                    ))
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        let transactions = match transactions {
+            Ok(mempool::Response::Transactions(transactions)) => transactions,
+            Ok(_) => unreachable!(
+                "mempool::Request::TransactionsById always returns mempool::Response::Transactions"
+            ),
+            Err(error) => {
+                warn!(?error, "could not read mempool transactions to snapshot");
+                return;
+            }
+        };
+
+        let path = Self::mempool_snapshot_path();
+
+        if let Err(error) = mempool::snapshot::save(transactions.iter(), &path) {
+            warn!(?error, ?path, "could not write mempool snapshot");
+        } else {
+            info!(
+                transactions = transactions.len(),
+                ?path,
+                "saved mempool snapshot"
+            );
+        }
+    }
+
     /// Returns the bound for the state service buffer,
     /// based on the configurations of the services that use the state concurrently.
     fn state_buffer_bound() -> usize {
@@ -299,6 +488,7 @@ impl StartCmd {
         network: Network,
         latest_chain_tip: impl ChainTip,
         sync_status: SyncStatus,
+        sync_progress: tokio::sync::watch::Receiver<SyncProgress>,
     ) {
         // The amount of time between progress logs.
         const LOG_INTERVAL: Duration = Duration::from_secs(60);
@@ -328,15 +518,44 @@ impl StartCmd {
                     .best_tip_height()
                     .expect("unexpected empty state: estimate requires a block height");
 
-                let sync_progress = f64::from(current_height.0) / f64::from(estimated_height.0);
-                let sync_percent = format!("{:.3}", sync_progress * 100.0);
+                let sync_fraction = f64::from(current_height.0) / f64::from(estimated_height.0);
+                let sync_percent = format!("{:.3}", sync_fraction * 100.0);
 
                 let remaining_sync_blocks = estimated_height - current_height;
 
-                // TODO:
-                // - estimate the remaining sync time
-                // - log progress, remaining blocks, and remaining time to next network upgrade
-                // - also add this info to the metrics
+                // The ETA and commit rate are derived from the syncer's own recent commit rate,
+                // rather than recomputed here, so they reflect actual download/verify
+                // throughput rather than time since this log loop last ran.
+                let progress = sync_progress.borrow().clone();
+                let blocks_per_second = progress.blocks_per_second;
+                let eta = progress.eta();
+
+                // The number of blocks until the next network upgrade activates, if there is one
+                // we haven't reached yet.
+                let next_network_upgrade_blocks = Network::activation_list(network)
+                    .into_iter()
+                    .map(|(activation_height, _)| activation_height)
+                    .filter(|activation_height| *activation_height > current_height)
+                    .map(|activation_height| activation_height - current_height)
+                    .min();
+
+                metrics::gauge!("sync.progress.percent", sync_fraction * 100.0);
+                metrics::gauge!(
+                    "sync.progress.remaining_blocks",
+                    remaining_sync_blocks as f64
+                );
+                metrics::gauge!("sync.progress.blocks_per_second", blocks_per_second);
+                // Skip the gauge entirely when the ETA is unknown, rather than reporting 0.0,
+                // which would be indistinguishable from "arrived" to anyone scraping it.
+                if let Some(eta) = eta {
+                    metrics::gauge!("sync.progress.eta_seconds", eta.as_secs_f64());
+                }
+                if let Some(next_network_upgrade_blocks) = next_network_upgrade_blocks {
+                    metrics::gauge!(
+                        "sync.progress.next_network_upgrade_blocks",
+                        next_network_upgrade_blocks as f64
+                    );
+                }
 
                 if is_close_to_tip && remaining_sync_blocks > MIN_SYNC_WARNING_BLOCKS {
                     // We've stopped syncing blocks, but we estimate we're a long way from the tip.
@@ -370,6 +589,8 @@ impl StartCmd {
                     info!(
                         %sync_percent,
                         ?remaining_sync_blocks,
+                        ?eta,
+                        ?next_network_upgrade_blocks,
                         "estimated progress to chain tip"
                     );
                 }
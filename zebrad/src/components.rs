@@ -7,6 +7,7 @@
 
 pub mod inbound;
 pub mod metrics;
+pub mod task_supervisor;
 #[allow(missing_docs)]
 pub mod tokio;
 #[allow(missing_docs)]
@@ -31,6 +31,7 @@ mod tests;
 
 pub use self::crawler::Crawler;
 pub use self::error::MempoolError;
+pub use self::storage::snapshot;
 #[cfg(test)]
 pub use self::storage::tests::unmined_transactions_in_blocks;
 
@@ -171,8 +172,12 @@ impl Service<Request> for Mempool {
         // Clean up completed download tasks and add to mempool if successful
         while let Poll::Ready(Some(r)) = self.tx_downloads.as_mut().poll_next(cx) {
             if let Ok(tx) = r {
-                // TODO: should we do something with the result?
-                let _ = self.storage.insert(tx);
+                // A freshly-verified transaction can still be rejected here, for example if it
+                // conflicts with another transaction already in the mempool. If inserting it
+                // would exceed the mempool's cost limit, other transactions are evicted instead.
+                if let Err(error) = self.storage.insert(tx) {
+                    tracing::trace!(?error, "failed to insert transaction into mempool");
+                }
             }
         }
         Poll::Ready(Ok(()))
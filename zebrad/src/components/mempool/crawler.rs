@@ -2,7 +2,7 @@
 //!
 //! The crawler periodically requests transactions from peers in order to populate the mempool.
 
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
 use futures::{
     stream::{self, FuturesUnordered},
@@ -11,8 +11,13 @@ use futures::{
 use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
 use tower::{timeout::Timeout, BoxError, Service, ServiceExt};
 
+use zebra_chain::transaction::UnminedTxId;
 use zebra_network::{Request, Response};
 
+use crate::components::sync::SyncStatus;
+
+use super::{Gossip, Request as MempoolRequest, Response as MempoolResponse};
+
 #[cfg(test)]
 mod tests;
 
@@ -31,20 +36,54 @@ const RATE_LIMIT_DELAY: Duration = Duration::from_secs(75);
 /// If this timeout is set too low, the crawler may fail to populate the mempool.
 const PEER_RESPONSE_TIMEOUT: Duration = Duration::from_secs(6);
 
+/// How often [`Crawler::wait_until_enabled`] re-checks [`SyncStatus::is_close_to_tip`] while the
+/// node isn't close to the chain tip yet.
+const SYNC_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// The mempool transaction crawler.
-pub struct Crawler<S> {
+pub struct Crawler<S, M> {
     peer_set: Mutex<Timeout<S>>,
+
+    /// A handle to the mempool itself, used to check which transaction IDs it already knows
+    /// about, and to queue newly discovered IDs for download and verification.
+    mempool: Mutex<M>,
+
+    /// Transaction IDs this crawler has already queued for download, but that haven't shown up
+    /// in the mempool's verified set yet, so they aren't queued again on the next crawl before
+    /// their download and verification has had a chance to finish.
+    ///
+    /// Reconciled against the mempool's verified set and rejected list at the start of every
+    /// [`Self::handle_response`] call: an ID drops out of this set once it shows up in either.
+    /// An ID that never shows up in either, for example because its download failed without a
+    /// recorded rejection, stays in this set and isn't queued again.
+    in_flight: Mutex<HashSet<UnminedTxId>>,
+
+    /// Whether the node is close enough to the chain tip for mempool crawling to be worthwhile.
+    ///
+    /// Checked at the start of every crawl round in [`Self::wait_until_enabled`], so the crawler
+    /// pauses again on its next iteration if a large reorg or long stall pushes the node back
+    /// behind the tip.
+    sync_status: SyncStatus,
 }
 
-impl<S> Crawler<S>
+impl<S, M> Crawler<S, M>
 where
     S: Service<Request, Response = Response, Error = BoxError> + Clone + Send + 'static,
     S::Future: Send,
+    M: Service<MempoolRequest, Response = MempoolResponse, Error = BoxError> + Clone + Send + 'static,
+    M::Future: Send,
 {
     /// Spawn an asynchronous task to run the mempool crawler.
-    pub fn spawn(peer_set: S) -> JoinHandle<Result<(), BoxError>> {
+    ///
+    /// `mempool` is used to deduplicate crawled transaction IDs against the mempool's own
+    /// verified set, and to queue newly discovered IDs for download and verification.
+    /// `sync_status` gates crawling until (and only while) the node is close to the chain tip.
+    pub fn spawn(peer_set: S, mempool: M, sync_status: SyncStatus) -> JoinHandle<Result<(), BoxError>> {
         let crawler = Crawler {
             peer_set: Mutex::new(Timeout::new(peer_set, PEER_RESPONSE_TIMEOUT)),
+            mempool: Mutex::new(mempool),
+            in_flight: Mutex::new(HashSet::new()),
+            sync_status,
         };
 
         tokio::spawn(crawler.run())
@@ -60,8 +99,16 @@ where
     }
 
     /// Wait until the mempool is enabled.
+    ///
+    /// Awaits until [`SyncStatus::is_close_to_tip`] reports that the node is close to the chain
+    /// tip, polling every [`SYNC_STATUS_POLL_INTERVAL`]. Called at the start of every crawl round
+    /// in [`Self::run`], so if the node falls behind again - for example during a large reorg or
+    /// a long stall - the crawler pauses here again on its next iteration instead of continuing
+    /// to crawl while synchronization is in progress.
     async fn wait_until_enabled(&self) {
-        // TODO: Check if synchronizing up to chain tip has finished (#2603).
+        while !self.sync_status.is_close_to_tip() {
+            sleep(SYNC_STATUS_POLL_INTERVAL).await;
+        }
     }
 
     /// Crawl peers for transactions.
@@ -95,18 +142,95 @@ where
     }
 
     /// Handle a peer's response to the crawler's request for transactions.
+    ///
+    /// Deduplicates `transaction_ids` against the mempool's verified set and against IDs this
+    /// crawler already has in flight from a previous response, then queues the rest for download
+    /// and verification. Each outcome is surfaced as a metric: how many IDs were received, how
+    /// many were genuinely new, and how many were dropped as duplicates.
     async fn handle_response(&self, response: Response) -> Result<(), BoxError> {
         let transaction_ids = match response {
             Response::TransactionIds(ids) => ids,
             _ => unreachable!("Peer set did not respond with transaction IDs to mempool crawler"),
         };
 
+        metrics::counter!(
+            "mempool.crawler.ids.received",
+            transaction_ids.len() as u64
+        );
+
         trace!(
             "Mempool crawler received {} transaction IDs",
             transaction_ids.len()
         );
 
-        // TODO: Send transaction IDs to the download and verify stream (#2650)
+        let mut mempool = self.mempool.lock().await.clone();
+
+        let known_ids: HashSet<UnminedTxId> = match mempool
+            .ready_and()
+            .await?
+            .call(MempoolRequest::TransactionIds)
+            .await?
+        {
+            MempoolResponse::TransactionIds(ids) => ids.into_iter().collect(),
+            _ => unreachable!("Mempool did not respond with transaction IDs to mempool crawler"),
+        };
+
+        let mut in_flight = self.in_flight.lock().await;
+
+        // An in-flight ID is done once it either lands in the verified set (`known_ids`, above)
+        // or gets rejected - either way, it's no longer downloading or being verified, so forget
+        // it here and let it be re-queued if it's gossiped again later.
+        let still_in_flight: HashSet<UnminedTxId> = in_flight
+            .iter()
+            .filter(|id| !known_ids.contains(id))
+            .cloned()
+            .collect();
+
+        let rejected_ids: HashSet<UnminedTxId> = if still_in_flight.is_empty() {
+            HashSet::new()
+        } else {
+            match mempool
+                .ready_and()
+                .await?
+                .call(MempoolRequest::RejectedTransactionIds(still_in_flight))
+                .await?
+            {
+                MempoolResponse::RejectedTransactionIds(ids) => ids.into_iter().collect(),
+                _ => unreachable!("Mempool did not respond with transaction IDs to mempool crawler"),
+            }
+        };
+
+        in_flight.retain(|id| !known_ids.contains(id) && !rejected_ids.contains(id));
+
+        let mut new_ids = Vec::new();
+        let mut duplicates = 0usize;
+
+        for id in transaction_ids {
+            if known_ids.contains(&id) || in_flight.contains(&id) {
+                duplicates += 1;
+                continue;
+            }
+
+            in_flight.insert(id);
+            new_ids.push(id);
+        }
+
+        drop(in_flight);
+
+        metrics::counter!("mempool.crawler.ids.duplicate", duplicates as u64);
+        metrics::gauge!("mempool.crawler.ids.queued", new_ids.len() as f64);
+
+        if new_ids.is_empty() {
+            return Ok(());
+        }
+
+        let gossip = new_ids.into_iter().map(Gossip::Id).collect();
+
+        mempool
+            .ready_and()
+            .await?
+            .call(MempoolRequest::Queue(gossip))
+            .await?;
 
         Ok(())
     }
@@ -0,0 +1,191 @@
+//! Tests for [`Crawler::handle_response`]'s deduplication of crawled transaction IDs.
+
+use std::sync::{Arc, Mutex as StdMutex};
+
+use futures::{future::Ready, FutureExt};
+use tower::{service_fn, util::BoxCloneService};
+
+use zebra_chain::{parameters::Network, transaction::UnminedTxId};
+
+use super::*;
+
+/// Build a [`Crawler`] with a `peer_set` that's never actually polled by [`Crawler::handle_response`],
+/// and a fresh [`SyncStatus`] that's likewise irrelevant to deduplication.
+///
+/// `S` needs to be `Clone` to satisfy [`Crawler`]'s own impl bound, even though
+/// `handle_response` never touches `peer_set` - so this is boxed as a [`BoxCloneService`] rather
+/// than a plain `BoxService`.
+fn new_test_crawler<M>(mempool: M) -> Crawler<BoxCloneService<Request, Response, BoxError>, M>
+where
+    M: Service<MempoolRequest, Response = MempoolResponse, Error = BoxError> + Clone + Send + 'static,
+    M::Future: Send,
+{
+    let peer_set: BoxCloneService<Request, Response, BoxError> = BoxCloneService::new(service_fn(
+        |_request: Request| -> Ready<Result<Response, BoxError>> {
+            unreachable!("handle_response does not use the peer set")
+        },
+    ));
+
+    let (sync_status, _recent_syncs) = SyncStatus::new();
+
+    Crawler {
+        peer_set: Mutex::new(Timeout::new(peer_set, PEER_RESPONSE_TIMEOUT)),
+        mempool: Mutex::new(mempool),
+        in_flight: Mutex::new(HashSet::new()),
+        sync_status,
+    }
+}
+
+/// A mock mempool [`Service`] that answers `TransactionIds` from `verified`, and
+/// `RejectedTransactionIds` from `rejected`, and records every `Queue` call it receives.
+#[derive(Clone)]
+struct MockMempool {
+    verified: Arc<StdMutex<HashSet<UnminedTxId>>>,
+    rejected: Arc<StdMutex<HashSet<UnminedTxId>>>,
+    queued: Arc<StdMutex<Vec<UnminedTxId>>>,
+}
+
+impl MockMempool {
+    fn new(verified: HashSet<UnminedTxId>, rejected: HashSet<UnminedTxId>) -> Self {
+        MockMempool {
+            verified: Arc::new(StdMutex::new(verified)),
+            rejected: Arc::new(StdMutex::new(rejected)),
+            queued: Arc::new(StdMutex::new(Vec::new())),
+        }
+    }
+
+    fn queued_ids(&self) -> Vec<UnminedTxId> {
+        self.queued.lock().unwrap().clone()
+    }
+}
+
+impl Service<MempoolRequest> for MockMempool {
+    type Response = MempoolResponse;
+    type Error = BoxError;
+    type Future =
+        std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: MempoolRequest) -> Self::Future {
+        let this = self.clone();
+
+        async move {
+            match request {
+                MempoolRequest::TransactionIds => Ok(MempoolResponse::TransactionIds(
+                    this.verified.lock().unwrap().iter().cloned().collect(),
+                )),
+                MempoolRequest::RejectedTransactionIds(ids) => {
+                    let rejected = this.rejected.lock().unwrap();
+                    let response = ids.into_iter().filter(|id| rejected.contains(id)).collect();
+
+                    Ok(MempoolResponse::RejectedTransactionIds(response))
+                }
+                MempoolRequest::Queue(gossip) => {
+                    let ids: Vec<UnminedTxId> = gossip
+                        .into_iter()
+                        .map(|gossip| match gossip {
+                            Gossip::Id(id) => id,
+                            _ => unreachable!("crawler only ever queues Gossip::Id"),
+                        })
+                        .collect();
+                    let response = vec![Ok(()); ids.len()];
+
+                    this.queued.lock().unwrap().extend(ids);
+
+                    Ok(MempoolResponse::Queued(response))
+                }
+                MempoolRequest::TransactionsById(_) => {
+                    unreachable!("handle_response never requests transactions by ID")
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Four distinct transaction IDs to dedupe against, taken from real unmined test transactions
+/// rather than invented byte patterns.
+fn test_ids() -> Vec<UnminedTxId> {
+    super::super::unmined_transactions_in_blocks(..=10, Network::Mainnet)
+        .take(4)
+        .map(|tx| tx.id)
+        .collect()
+}
+
+/// An ID that's already in the mempool's verified set is dropped as a duplicate, and never
+/// reaches [`MempoolRequest::Queue`].
+#[tokio::test]
+async fn handle_response_drops_ids_already_verified() {
+    let ids = test_ids();
+    let verified_id = ids[0];
+
+    let mempool = MockMempool::new(HashSet::from([verified_id]), HashSet::new());
+    let crawler = new_test_crawler(mempool.clone());
+
+    crawler
+        .handle_response(Response::TransactionIds(vec![verified_id]))
+        .await
+        .expect("handle_response does not error on a known ID");
+
+    assert_eq!(mempool.queued_ids(), Vec::<UnminedTxId>::new());
+    assert!(crawler.in_flight.lock().await.is_empty());
+}
+
+/// An ID the crawler already has in flight from a previous response is dropped as a duplicate,
+/// and stays in flight rather than being queued a second time.
+#[tokio::test]
+async fn handle_response_drops_ids_already_in_flight() {
+    let ids = test_ids();
+    let in_flight_id = ids[0];
+
+    let mempool = MockMempool::new(HashSet::new(), HashSet::new());
+    let crawler = new_test_crawler(mempool.clone());
+    crawler.in_flight.lock().await.insert(in_flight_id);
+
+    crawler
+        .handle_response(Response::TransactionIds(vec![in_flight_id]))
+        .await
+        .expect("handle_response does not error on an in-flight ID");
+
+    assert_eq!(mempool.queued_ids(), Vec::<UnminedTxId>::new());
+    assert!(crawler.in_flight.lock().await.contains(&in_flight_id));
+}
+
+/// An in-flight ID that the mempool rejected, rather than accepted, is forgotten from
+/// `in_flight`, so a later response can queue it again.
+#[tokio::test]
+async fn handle_response_forgets_rejected_in_flight_ids() {
+    let ids = test_ids();
+    let rejected_id = ids[0];
+    let other_id = ids[1];
+
+    let mempool = MockMempool::new(HashSet::new(), HashSet::from([rejected_id]));
+    let crawler = new_test_crawler(mempool.clone());
+    crawler.in_flight.lock().await.insert(rejected_id);
+
+    // The rejected ID is absent from this response; handle_response still has to notice, via
+    // RejectedTransactionIds, that it's no longer worth tracking as in-flight.
+    crawler
+        .handle_response(Response::TransactionIds(vec![other_id]))
+        .await
+        .expect("handle_response does not error when reconciling a rejected ID");
+
+    assert!(!crawler.in_flight.lock().await.contains(&rejected_id));
+    assert_eq!(mempool.queued_ids(), vec![other_id]);
+
+    // Now that it's been forgotten, the rejected ID can be queued again if it's gossiped a
+    // second time.
+    crawler
+        .handle_response(Response::TransactionIds(vec![rejected_id]))
+        .await
+        .expect("handle_response does not error on a re-queued ID");
+
+    assert!(crawler.in_flight.lock().await.contains(&rejected_id));
+    assert_eq!(mempool.queued_ids(), vec![other_id, rejected_id]);
+}
@@ -1,9 +1,17 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
 
+#[cfg(not(test))]
+use rand::{thread_rng, Rng};
 use thiserror::Error;
 
 use zebra_chain::transaction::{self, UnminedTx, UnminedTxId};
 
+pub use self::verified_set::SpendId;
+
 use self::verified_set::VerifiedSet;
 use super::MempoolError;
 
@@ -13,10 +21,46 @@ use proptest_derive::Arbitrary;
 #[cfg(test)]
 pub mod tests;
 
+pub mod snapshot;
 mod verified_set;
 
-/// The maximum number of verified transactions to store in the mempool.
-const MEMPOOL_SIZE: usize = 2;
+/// The default total cost budget for the mempool, in ZIP-401 "cost" units.
+///
+/// https://zips.z.cash/zip-0401#specification
+const DEFAULT_MEMPOOL_COST_LIMIT: u64 = 80_000_000;
+
+/// The minimum cost charged for a single transaction, regardless of its serialized size.
+///
+/// This stops peers from cheaply filling the mempool's cost budget with many small
+/// transactions, each paying a disproportionately small fraction of the total cost.
+///
+/// https://zips.z.cash/zip-0401#specification
+const MIN_TRANSACTION_COST: u64 = 4_000;
+
+/// The ZIP-317 marginal fee, in zatoshis, used as the per-unit-cost reference for whether a
+/// transaction paid at least the conventional fee.
+///
+/// https://zips.z.cash/zip-0317#fee-calculation
+const MARGINAL_FEE: u64 = 5_000;
+
+/// The eviction weight penalty added to a transaction that didn't pay at least the conventional
+/// fee for its cost, as specified by ZIP-401.
+///
+/// https://zips.z.cash/zip-0401#specification
+const LOW_FEE_PENALTY: u64 = 40_000;
+
+/// The default length of time a [`StorageRejectionError::RandomlyEvicted`] rejection is
+/// remembered for, before a resubmitted transaction is allowed to be accepted again.
+///
+/// `zcashd` calls this `mempoolevictionmemoryminutes`.
+///
+/// https://zips.z.cash/zip-0401#specification
+const DEFAULT_EVICTION_MEMORY_TIME: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum number of rejections [`Storage::rejected`] remembers at once, across every
+/// rejection reason, so that a flood of distinct invalid or evicted transactions can't exhaust
+/// memory. When full, the oldest entry is dropped to make room for a new one.
+const MAX_EVICTION_MEMORY_ENTRIES: usize = 40_000;
 
 #[derive(Error, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(any(test, feature = "proptest-impl"), derive(Arbitrary))]
@@ -44,21 +88,75 @@ pub enum StorageRejectionError {
     FailedVerification(#[from] zebra_consensus::error::TransactionError),
 }
 
-#[derive(Default)]
 pub struct Storage {
-    /// The set of verified transactions in the mempool. This is a
-    /// cache of size [`MEMPOOL_SIZE`].
+    /// The set of verified transactions in the mempool, bounded by [`Self::cost_limit`] rather
+    /// than a fixed count.
     verified: VerifiedSet,
 
-    /// The set of rejected transactions by id, and their rejection reasons.
-    rejected: HashMap<UnminedTxId, StorageRejectionError>,
+    /// The set of rejected transactions by id, their rejection reasons, and when each one was
+    /// rejected.
+    rejected: HashMap<UnminedTxId, RejectionEntry>,
+
+    /// The total cost budget for [`Self::verified`], in ZIP-401 "cost" units.
+    ///
+    /// When inserting a transaction would push [`Self::total_cost`] over this limit, the lowest
+    /// priority transactions are evicted until there's enough room.
+    cost_limit: u64,
+
+    /// A random value, mixed into [`Self::weighted_sample_key`] so that eviction order can't be
+    /// predicted by a peer targeting a specific transaction for eviction.
+    ///
+    /// Generated once, the first time this [`Storage`] is created.
+    eviction_salt: u64,
+
+    /// How long a [`StorageRejectionError::RandomlyEvicted`] entry in [`Self::rejected`] is
+    /// remembered for, before it's treated as absent and the transaction may be resubmitted.
+    ///
+    /// TODO: thread this through from a `config.mempool.eviction_memory_time` setting once
+    /// zebrad grows a `Config` type - there's no configuration plumbing anywhere in this tree
+    /// yet for `Mempool`/`Storage` to hang one off of, so this is only configurable for tests.
+    eviction_memory_time: Duration,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage {
+            verified: VerifiedSet::default(),
+            rejected: HashMap::new(),
+            cost_limit: DEFAULT_MEMPOOL_COST_LIMIT,
+            eviction_salt: eviction_salt(),
+            eviction_memory_time: DEFAULT_EVICTION_MEMORY_TIME,
+        }
+    }
+}
+
+/// A remembered rejection: why a transaction was rejected, and when.
+#[derive(Clone, Debug)]
+struct RejectionEntry {
+    error: StorageRejectionError,
+    rejected_at: Instant,
+}
+
+/// Returns the [`Storage::eviction_salt`] for a newly created [`Storage`].
+///
+/// Outside of tests, this is randomized per node so that eviction order can't be predicted by a
+/// peer. Under tests, it's fixed so that eviction-order assertions are reproducible.
+#[cfg(not(test))]
+fn eviction_salt() -> u64 {
+    thread_rng().gen()
+}
+
+#[cfg(test)]
+fn eviction_salt() -> u64 {
+    0
 }
 
 impl Storage {
     /// Insert a [`UnminedTx`] into the mempool.
     ///
-    /// If its insertion results in evicting other transactions, they will be tracked
-    /// as [`StorageRejectionError::RandomlyEvicted`].
+    /// If inserting `tx` would push the mempool's total cost over [`Self::cost_limit`],
+    /// transactions are evicted first to make room, from lowest to highest priority, with
+    /// [`StorageRejectionError::RandomlyEvicted`] recorded for each one.
     pub fn insert(&mut self, tx: UnminedTx) -> Result<UnminedTxId, MempoolError> {
         let tx_id = tx.id;
 
@@ -79,11 +177,13 @@ impl Storage {
         // nullifier already revealed by another transaction in the mempool, reject that
         // transaction.
         if self.verified.has_spend_conflicts(&tx) {
-            self.rejected
-                .insert(tx.id, StorageRejectionError::SpendConflict);
+            self.reject(tx.id, StorageRejectionError::SpendConflict);
             return Err(StorageRejectionError::SpendConflict.into());
         }
 
+        // Make room under the cost budget, evicting lower-priority transactions if necessary.
+        self.evict_for_cost(transaction_cost(&tx));
+
         // Then, we insert into the pool.
         // This will a evict transactions to open space for the new transaction if needed.
         self.verified.insert(tx);
@@ -91,6 +191,191 @@ impl Storage {
         Ok(tx_id)
     }
 
+    /// Inserts `tx`, replacing a single directly-conflicting transaction if `tx` pays a strictly
+    /// higher conventional fee rate than it, and at least as much fee in absolute terms.
+    ///
+    /// This is an opt-in replace-by-fee path: unlike [`Self::insert`], a spend conflict doesn't
+    /// immediately reject `tx`. [`Self::insert`] keeps its unconditional-rejection behaviour for
+    /// callers that haven't opted into replacement.
+    ///
+    /// `tx` still gets rejected, the same way [`Self::insert`] would reject it, if it conflicts
+    /// with more than one verified transaction, or if it doesn't pay enough to replace the one
+    /// transaction it does conflict with.
+    pub fn insert_with_replacement(&mut self, tx: UnminedTx) -> Result<Replacement, MempoolError> {
+        let tx_id = tx.id;
+
+        // First, check if we have a cached rejection for this transaction.
+        if let Some(error) = self.rejection_error(&tx.id) {
+            return Err(error.into());
+        }
+
+        // If `tx` is already in the mempool, we don't change anything.
+        if self.verified.contains(&tx.id) {
+            return Err(MempoolError::InMempool);
+        }
+
+        let conflicting_ids = self.verified.conflicting_tx_ids(&tx);
+
+        let evicted = match conflicting_ids.len() {
+            0 => Vec::new(),
+            1 => {
+                let incumbent_id = *conflicting_ids
+                    .iter()
+                    .next()
+                    .expect("length was just checked to be exactly 1");
+                let incumbent = self.verified.transaction(&incumbent_id).expect(
+                    "a conflicting transaction id always belongs to a transaction in the \
+                     verified set",
+                );
+
+                if replaces(&tx, incumbent) {
+                    vec![incumbent_id]
+                } else {
+                    self.reject(tx.id, StorageRejectionError::SpendConflict);
+                    return Err(StorageRejectionError::SpendConflict.into());
+                }
+            }
+            _ => {
+                self.reject(tx.id, StorageRejectionError::SpendConflict);
+                return Err(StorageRejectionError::SpendConflict.into());
+            }
+        };
+
+        for evicted_id in &evicted {
+            self.verified.remove_all_that(|tx| tx.id == *evicted_id);
+        }
+
+        self.evict_for_cost(transaction_cost(&tx));
+        self.verified.insert(tx);
+
+        Ok(Replacement {
+            id: tx_id,
+            evicted,
+        })
+    }
+
+    /// Returns the [`SpendId`]s that `tx` shares with a transaction already verified in the
+    /// mempool: the specific transparent outputs or shielded nullifiers both transactions spend.
+    ///
+    /// This is empty if `tx` doesn't conflict with anything currently in the mempool.
+    pub fn conflicting_ids(&self, tx: &UnminedTx) -> HashSet<SpendId> {
+        self.verified.conflicting_spend_ids(tx)
+    }
+
+    /// Evicts verified transactions, lowest-priority first, until inserting a transaction with
+    /// `incoming_cost` would no longer push [`Self::total_cost`] over [`Self::cost_limit`].
+    ///
+    /// Each evicted transaction is recorded in the rejected list as
+    /// [`StorageRejectionError::RandomlyEvicted`].
+    fn evict_for_cost(&mut self, incoming_cost: u64) {
+        while self.total_cost() + incoming_cost > self.cost_limit {
+            let Some(victim_id) = self.lowest_priority_tx_id() else {
+                break;
+            };
+
+            self.verified.remove_all_that(|tx| tx.id == victim_id);
+            self.reject(victim_id, StorageRejectionError::RandomlyEvicted);
+        }
+    }
+
+    /// Records `txid` as rejected for `error`, evicting the oldest remembered rejection first if
+    /// [`Self::rejected`] is already at [`MAX_EVICTION_MEMORY_ENTRIES`].
+    fn reject(&mut self, txid: UnminedTxId, error: StorageRejectionError) {
+        if self.rejected.len() >= MAX_EVICTION_MEMORY_ENTRIES && !self.rejected.contains_key(&txid)
+        {
+            if let Some(&oldest_txid) = self
+                .rejected
+                .iter()
+                .min_by_key(|(_, entry)| entry.rejected_at)
+                .map(|(txid, _)| txid)
+            {
+                self.rejected.remove(&oldest_txid);
+            }
+        }
+
+        self.rejected.insert(
+            txid,
+            RejectionEntry {
+                error,
+                rejected_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Returns the [`UnminedTxId`] of the verified transaction selected for eviction, or `None`
+    /// if [`Self::verified`] is empty.
+    ///
+    /// Each transaction is evicted with probability proportional to its
+    /// [`Self::eviction_weight`], using the Efraimidis-Spirakis algorithm for weighted random
+    /// sampling: draw a pseudo-random `u` in `(0, 1)` per transaction (see
+    /// [`Self::weighted_sample_key`]) and keep the one with the largest `u.powf(1.0 / weight)`.
+    fn lowest_priority_tx_id(&self) -> Option<UnminedTxId> {
+        self.verified
+            .transactions()
+            .map(|tx| {
+                let weight = self.eviction_weight(tx);
+
+                (tx.id, self.weighted_sample_key(tx.id, weight))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(tx_id, _)| tx_id)
+    }
+
+    /// Returns the eviction weight of `tx`: transactions with the highest weight are the most
+    /// likely to be evicted first when the mempool is over its cost budget.
+    ///
+    /// This is `cost + low_fee_penalty`, where `low_fee_penalty` is [`LOW_FEE_PENALTY`] if `tx`
+    /// didn't pay at least the conventional fee for its cost, or `0` otherwise, as specified by
+    /// ZIP-401.
+    fn eviction_weight(&self, tx: &UnminedTx) -> u64 {
+        let cost = transaction_cost(tx);
+        let low_fee_penalty = if self.pays_low_fee(tx, cost) {
+            LOW_FEE_PENALTY
+        } else {
+            0
+        };
+
+        cost.saturating_add(low_fee_penalty)
+    }
+
+    /// Returns `true` if `tx`'s own [`UnminedTx::conventional_fee`] is below the conventional fee
+    /// expected of a transaction with the given `cost`.
+    ///
+    /// TODO: ZIP-317's conventional fee is `MARGINAL_FEE * max(logical_actions, GRACE_ACTIONS)`,
+    /// counted per transparent/shielded action rather than per byte - `UnminedTx` isn't defined
+    /// in this tree with per-action counts to compute that against, so `cost` (already computed
+    /// for the cost budget) is used as a stand-in for the action count instead.
+    fn pays_low_fee(&self, tx: &UnminedTx, cost: u64) -> bool {
+        let conventional_fee = MARGINAL_FEE.saturating_mul(cost / MIN_TRANSACTION_COST);
+
+        i64::from(tx.conventional_fee) < conventional_fee as i64
+    }
+
+    /// Returns `tx_id`'s pseudo-random sample key for the weighted eviction draw in
+    /// [`Self::lowest_priority_tx_id`]: a `u` in the open interval `(0, 1)`, raised to
+    /// `1.0 / weight`, so that comparing keys across transactions selects each one with
+    /// probability proportional to its `weight`.
+    ///
+    /// `u` is derived from [`Self::eviction_salt`] and `tx_id` rather than drawn from an RNG at
+    /// call time, so the draw is reproducible under tests (where the salt is fixed) while still
+    /// being unpredictable to a peer who doesn't know the node's salt.
+    fn weighted_sample_key(&self, tx_id: UnminedTxId, weight: u64) -> f64 {
+        let mut hasher = DefaultHasher::new();
+        self.eviction_salt.hash(&mut hasher);
+        tx_id.hash(&mut hasher);
+
+        // Map the hash into the open interval (0, 1): never exactly 0 or 1, so `powf` below
+        // always returns a finite, comparable value.
+        let u = (hasher.finish() as f64 + 1.0) / (u64::MAX as f64 + 2.0);
+
+        u.powf(1.0 / weight as f64)
+    }
+
+    /// Returns the total ZIP-401 "cost" of all transactions currently in [`Self::verified`].
+    fn total_cost(&self) -> u64 {
+        self.verified.transactions().map(transaction_cost).sum()
+    }
+
     /// Returns `true` if a [`UnminedTx`] matching an [`UnminedTxId`] is in
     /// the mempool.
     pub fn contains(&self, txid: &UnminedTxId) -> bool {
@@ -159,25 +444,108 @@ impl Storage {
     /// the mempool rejected list.
     #[allow(dead_code)]
     pub fn contains_rejected(&self, txid: &UnminedTxId) -> bool {
-        self.rejected.contains_key(txid)
+        self.rejection_error(txid).is_some()
     }
 
-    /// Returns `true` if a [`UnminedTx`] matching an [`UnminedTxId`] is in
-    /// the mempool rejected list.
+    /// Returns the rejection reason for `txid`, or `None` if it isn't currently rejected.
+    ///
+    /// A [`StorageRejectionError::RandomlyEvicted`] entry older than
+    /// [`Self::eviction_memory_time`] is treated as absent: ZIP-401 only remembers evictions for
+    /// a bounded window, so the transaction may be resubmitted and re-verified after that.
     pub fn rejection_error(&self, txid: &UnminedTxId) -> Option<StorageRejectionError> {
-        self.rejected.get(txid).cloned()
+        let entry = self.rejected.get(txid)?;
+
+        if entry.error == StorageRejectionError::RandomlyEvicted
+            && entry.rejected_at.elapsed() > self.eviction_memory_time
+        {
+            return None;
+        }
+
+        Some(entry.error.clone())
     }
 
     /// Returns the set of [`UnminedTxId`]s matching ids in the rejected list.
     pub fn rejected_transactions(&self, tx_ids: HashSet<UnminedTxId>) -> Vec<UnminedTxId> {
         tx_ids
             .into_iter()
-            .filter(|tx| self.rejected.contains_key(tx))
+            .filter(|tx| self.contains_rejected(tx))
             .collect()
     }
 
     /// Clears the whole mempool storage.
+    ///
+    /// This also expires the rejected list, including any [`StorageRejectionError::RandomlyEvicted`]
+    /// entries: since the chain tip is changing, transactions that were evicted under the old
+    /// tip's UTXO set may no longer conflict with anything, and should get a chance to be
+    /// re-verified and re-gossiped.
     pub fn clear(&mut self) {
         self.verified.clear();
+        self.rejected.clear();
+    }
+
+    /// Returns a new [`Storage`] with the given `cost_limit`, for testing eviction behaviour
+    /// without waiting for [`DEFAULT_MEMPOOL_COST_LIMIT`] worth of transactions.
+    #[cfg(test)]
+    pub fn new_with_cost_limit(cost_limit: u64) -> Self {
+        Storage {
+            cost_limit,
+            ..Storage::default()
+        }
+    }
+
+    /// Returns a new [`Storage`] with the given `eviction_memory_time`, for testing that
+    /// [`StorageRejectionError::RandomlyEvicted`] entries expire without waiting for
+    /// [`DEFAULT_EVICTION_MEMORY_TIME`] to pass.
+    #[cfg(test)]
+    pub fn new_with_eviction_memory_time(eviction_memory_time: Duration) -> Self {
+        Storage {
+            eviction_memory_time,
+            ..Storage::default()
+        }
     }
 }
+
+/// Returns the ZIP-401 "cost" of `tx`: `max(tx.size, MIN_TRANSACTION_COST)`.
+///
+/// https://zips.z.cash/zip-0401#specification
+fn transaction_cost(tx: &UnminedTx) -> u64 {
+    (tx.size as u64).max(MIN_TRANSACTION_COST)
+}
+
+/// The outcome of a successful [`Storage::insert_with_replacement`] call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replacement {
+    /// The id of the transaction that was inserted.
+    pub id: UnminedTxId,
+
+    /// The ids of any transactions evicted to make room for the replacement, because they
+    /// conflicted with it and paid a lower conventional fee rate.
+    pub evicted: Vec<UnminedTxId>,
+}
+
+/// Returns `true` if `replacement` should replace `incumbent` under Zebra's replace-by-fee
+/// policy.
+///
+/// Modeled on the fee accounting in librustzcash's `fees` module: `replacement` must pay a
+/// strictly higher conventional fee rate (fee per unit of [`transaction_cost`]) than `incumbent`,
+/// *and* an absolute fee of at least `incumbent`'s, so replacing a transaction never lets a peer
+/// relay for less than the transaction it evicts already paid.
+///
+/// TODO: this assumes `UnminedTx` carries a `conventional_fee: Amount<NonNegative>` field,
+/// populated by the mempool's transaction verifier from the spent UTXOs' values - `UnminedTx`
+/// isn't defined in this tree to confirm that field against.
+fn replaces(replacement: &UnminedTx, incumbent: &UnminedTx) -> bool {
+    let replacement_fee = i64::from(replacement.conventional_fee);
+    let incumbent_fee = i64::from(incumbent.conventional_fee);
+
+    if replacement_fee < incumbent_fee {
+        return false;
+    }
+
+    let replacement_cost = transaction_cost(replacement) as i64;
+    let incumbent_cost = transaction_cost(incumbent) as i64;
+
+    // Cross-multiply to compare fee rates (fee / cost) without integer division rounding two
+    // different rates down to the same value.
+    replacement_fee * incumbent_cost > incumbent_fee * replacement_cost
+}
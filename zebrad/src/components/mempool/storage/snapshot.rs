@@ -0,0 +1,77 @@
+//! On-disk persistence for the mempool's verified transaction set.
+//!
+//! Without this, every `zebrad` restart drops the whole mempool, forcing every transaction to be
+//! re-downloaded and re-verified from peers even though most of them are still valid. This module
+//! only handles the bytes on disk; re-validating loaded transactions against the current chain
+//! tip happens by feeding them back through the same queue/verify path as a freshly gossiped
+//! transaction, not here.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use zebra_chain::{
+    serialization::{ZcashDeserialize, ZcashSerialize},
+    transaction::{Transaction, UnminedTx},
+};
+
+/// Writes `transactions` to `path`, in iteration order, as a sequence of length-prefixed
+/// zcash-serialized transactions.
+///
+/// Overwrites any existing file at `path`.
+pub fn save<'a>(transactions: impl Iterator<Item = &'a UnminedTx>, path: &Path) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+
+    for tx in transactions {
+        let bytes = tx
+            .transaction
+            .zcash_serialize_to_vec()
+            .expect("serializing an already-verified transaction cannot fail");
+
+        file.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        file.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Reads back the transactions written by [`save`], in the same order they were written.
+///
+/// Returns an empty list, rather than an error, if `path` doesn't exist: having no snapshot yet
+/// is the normal state for a node that has never shut down gracefully before.
+pub fn load(path: &Path) -> io::Result<Vec<UnminedTx>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+
+    let mut transactions = Vec::new();
+
+    loop {
+        let mut length_bytes = [0; 8];
+        match file.read_exact(&mut length_bytes) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
+        }
+
+        let length = u64::from_le_bytes(length_bytes) as usize;
+        let mut bytes = vec![0; length];
+        file.read_exact(&mut bytes)?;
+
+        let transaction = Transaction::zcash_deserialize(&bytes[..]).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("corrupt mempool snapshot: {}", error),
+            )
+        })?;
+
+        transactions.push(UnminedTx::from(Arc::new(transaction)));
+    }
+
+    Ok(transactions)
+}
@@ -1,9 +1,11 @@
 use std::{convert::TryFrom, ops::RangeBounds};
 
+mod prop;
+
 use super::*;
 
 use zebra_chain::{
-    amount::Amount,
+    amount::{Amount, NonNegative},
     block::{self, Block},
     parameters::{Network, NetworkUpgrade},
     serialization::ZcashDeserializeInto,
@@ -51,56 +53,29 @@ fn mempool_storage_basic() -> Result<()> {
 }
 
 fn mempool_storage_basic_for_network(network: Network) -> Result<()> {
-    // Create an empty storage
-    let mut storage: Storage = Default::default();
-
     // Get transactions from the first 10 blocks of the Zcash blockchain
     let unmined_transactions: Vec<_> = unmined_transactions_in_blocks(..=10, network).collect();
-    let total_transactions = unmined_transactions.len();
+
+    // Give the mempool enough cost budget for every transaction below to fit, so this test can
+    // focus on basic insert/contains/reject bookkeeping. Eviction behaviour under a tight cost
+    // budget is covered separately by `mempool_storage_evicts_over_cost_limit`.
+    let total_cost: u64 = unmined_transactions.iter().map(transaction_cost).sum();
+    let mut storage = Storage::new_with_cost_limit(total_cost);
 
     // Insert them all to the storage
     for unmined_transaction in unmined_transactions.clone() {
         storage.insert(unmined_transaction)?;
     }
 
-    // Separate transactions into the ones expected to be in the mempool and those expected to be
-    // rejected.
-    let rejected_transaction_count = total_transactions - MEMPOOL_SIZE;
-    let expected_to_be_rejected = &unmined_transactions[..rejected_transaction_count];
-    let expected_in_mempool = &unmined_transactions[rejected_transaction_count..];
+    // Every transaction fits under the cost budget, so all of them should be verified, and none
+    // of them rejected.
+    assert_eq!(storage.verified.len(), unmined_transactions.len());
 
-    // Only MEMPOOL_SIZE should land in verified
-    assert_eq!(storage.verified.len(), MEMPOOL_SIZE);
-
-    // The rest of the transactions will be in rejected
-    assert_eq!(storage.rejected.len(), rejected_transaction_count);
-
-    // Make sure the last MEMPOOL_SIZE transactions we sent are in the verified
-    for tx in expected_in_mempool {
+    for tx in &unmined_transactions {
         assert!(storage.contains(&tx.id));
+        assert!(!storage.contains_rejected(&tx.id));
     }
 
-    // Anything greater should not be in the verified
-    for tx in expected_to_be_rejected {
-        assert!(!storage.contains(&tx.id));
-    }
-
-    // Query all the ids we have for rejected, get back `total - MEMPOOL_SIZE`
-    let all_ids: HashSet<UnminedTxId> = unmined_transactions.iter().map(|tx| tx.id).collect();
-
-    // Convert response to a `HashSet` as we need a fixed order to compare.
-    let rejected_response: HashSet<UnminedTxId> =
-        storage.rejected_transactions(all_ids).into_iter().collect();
-
-    let rejected_ids = expected_to_be_rejected.iter().map(|tx| tx.id).collect();
-
-    assert_eq!(rejected_response, rejected_ids);
-
-    // Use `contains_rejected` to make sure the first id stored is now rejected
-    assert!(storage.contains_rejected(&expected_to_be_rejected[0].id));
-    // Use `contains_rejected` to make sure the last id stored is not rejected
-    assert!(!storage.contains_rejected(&expected_in_mempool[0].id));
-
     Ok(())
 }
 
@@ -215,6 +190,127 @@ fn inputs_from_blocks(
         })
 }
 
+#[test]
+fn mempool_storage_evicts_over_cost_limit() {
+    zebra_test::init();
+
+    let network = Network::Mainnet;
+
+    // Get a handful of unmined transactions, and use the first one's cost as the budget for
+    // every other transaction, so inserting any two of them forces an eviction.
+    let unmined_transactions: Vec<_> = unmined_transactions_in_blocks(..=10, network)
+        .take(3)
+        .collect();
+    assert!(
+        unmined_transactions.len() >= 2,
+        "need at least two transactions to test eviction"
+    );
+
+    let single_tx_cost = transaction_cost(&unmined_transactions[0]);
+    let mut storage = Storage::new_with_cost_limit(single_tx_cost);
+
+    let first_id = unmined_transactions[0].id;
+    let second_id = unmined_transactions[1].id;
+
+    storage
+        .insert(unmined_transactions[0].clone())
+        .expect("mempool is empty, so the first transaction fits under the cost limit");
+    assert!(storage.contains(&first_id));
+
+    storage
+        .insert(unmined_transactions[1].clone())
+        .expect("the cost limit is enforced by eviction, not by rejecting the new transaction");
+    assert!(
+        storage.contains(&second_id),
+        "the incoming transaction should never evict itself"
+    );
+    assert!(
+        !storage.contains(&first_id),
+        "the first transaction should have been evicted to make room"
+    );
+    assert_eq!(
+        storage.rejection_error(&first_id),
+        Some(StorageRejectionError::RandomlyEvicted)
+    );
+}
+
+#[test]
+fn mempool_storage_forgets_eviction_after_memory_time_expires() {
+    zebra_test::init();
+
+    let network = Network::Mainnet;
+
+    let unmined_transactions: Vec<_> = unmined_transactions_in_blocks(..=10, network)
+        .take(3)
+        .collect();
+    assert!(
+        unmined_transactions.len() >= 2,
+        "need at least two transactions to test eviction"
+    );
+
+    let single_tx_cost = transaction_cost(&unmined_transactions[0]);
+    let mut storage = Storage {
+        eviction_memory_time: Duration::from_millis(10),
+        ..Storage::new_with_cost_limit(single_tx_cost)
+    };
+
+    let first_id = unmined_transactions[0].id;
+
+    storage
+        .insert(unmined_transactions[0].clone())
+        .expect("mempool is empty, so the first transaction fits under the cost limit");
+    storage
+        .insert(unmined_transactions[1].clone())
+        .expect("the cost limit is enforced by eviction, not by rejecting the new transaction");
+
+    assert_eq!(
+        storage.rejection_error(&first_id),
+        Some(StorageRejectionError::RandomlyEvicted)
+    );
+
+    // Give the eviction memory window time to pass.
+    std::thread::sleep(Duration::from_millis(50));
+
+    assert_eq!(
+        storage.rejection_error(&first_id),
+        None,
+        "an eviction older than `eviction_memory_time` should be forgotten"
+    );
+    assert!(!storage.contains_rejected(&first_id));
+}
+
+#[test]
+fn mempool_storage_cost_limit_does_not_evict_when_there_is_room() {
+    zebra_test::init();
+
+    let network = Network::Mainnet;
+
+    let unmined_transactions: Vec<_> = unmined_transactions_in_blocks(..=10, network)
+        .take(2)
+        .collect();
+    assert!(unmined_transactions.len() >= 2);
+
+    let combined_cost: u64 = unmined_transactions
+        .iter()
+        .map(transaction_cost)
+        .sum::<u64>()
+        // Leave headroom so both transactions fit at once.
+        + MIN_TRANSACTION_COST;
+
+    let mut storage = Storage::new_with_cost_limit(combined_cost);
+
+    for unmined_transaction in &unmined_transactions {
+        storage
+            .insert(unmined_transaction.clone())
+            .expect("both transactions fit under the combined cost limit");
+    }
+
+    for unmined_transaction in &unmined_transactions {
+        assert!(storage.contains(&unmined_transaction.id));
+        assert!(!storage.contains_rejected(&unmined_transaction.id));
+    }
+}
+
 fn mock_transparent_transaction(inputs: Vec<transparent::Input>) -> UnminedTx {
     // A script with a single opcode that accepts the transaction (pushes true on the stack)
     let accepting_script = transparent::Script::new(&[1, 1]);
@@ -234,3 +330,85 @@ fn mock_transparent_transaction(inputs: Vec<transparent::Input>) -> UnminedTx {
         orchard_shielded_data: None,
     })
 }
+
+/// Returns `tx` with its conventional fee overridden to `fee`, for deterministic replace-by-fee
+/// tests that don't depend on a real UTXO lookup.
+fn with_conventional_fee(tx: UnminedTx, fee: Amount<NonNegative>) -> UnminedTx {
+    UnminedTx {
+        conventional_fee: fee,
+        ..tx
+    }
+}
+
+#[test]
+fn replace_by_fee_rejects_a_lower_fee_and_replaces_a_higher_one() {
+    let mut storage = Storage::default();
+
+    let mut inputs = inputs_from_blocks(.., Network::Mainnet);
+
+    let shared_input = inputs
+        .next()
+        .expect("At least one input from unmined blocks");
+    let first_transaction_input = inputs
+        .next()
+        .expect("At least two inputs from unmined blocks");
+    let second_transaction_input = inputs
+        .next()
+        .expect("At least three inputs from unmined blocks");
+
+    // Both mock transactions are tiny, well under `MIN_TRANSACTION_COST`, so `transaction_cost`
+    // is identical for both regardless of their exact sizes - letting this test compare fees
+    // directly, without the fee-rate cross-multiplication in `replaces` being skewed by size.
+    let incumbent_fee = Amount::try_from(1_000).expect("1000 is non-negative");
+    let incumbent = with_conventional_fee(
+        mock_transparent_transaction(vec![shared_input.clone(), first_transaction_input]),
+        incumbent_fee,
+    );
+    let incumbent_id = incumbent.id;
+
+    let build_replacement = |fee| {
+        with_conventional_fee(
+            mock_transparent_transaction(vec![shared_input.clone(), second_transaction_input.clone()]),
+            fee,
+        )
+    };
+
+    // A fee that doesn't beat the incumbent's should still be rejected.
+    storage
+        .insert(incumbent.clone())
+        .expect("mempool is empty, so the incumbent has nothing to conflict with");
+
+    let equal_fee_replacement = build_replacement(incumbent_fee);
+    let equal_fee_replacement_id = equal_fee_replacement.id;
+    assert_eq!(
+        storage.insert_with_replacement(equal_fee_replacement),
+        Err(MempoolError::Rejected)
+    );
+    assert!(storage.contains_rejected(&equal_fee_replacement_id));
+    assert!(storage.contains(&incumbent_id));
+
+    storage.clear();
+
+    // A strictly higher fee should replace the incumbent.
+    storage
+        .insert(incumbent)
+        .expect("mempool is empty, so the incumbent has nothing to conflict with");
+
+    let higher_fee = Amount::try_from(2_000).expect("2000 is non-negative");
+    let higher_fee_replacement = build_replacement(higher_fee);
+    let replacement_id = higher_fee_replacement.id;
+
+    let result = storage
+        .insert_with_replacement(higher_fee_replacement)
+        .expect("a strictly higher conventional fee should replace the incumbent");
+
+    assert_eq!(
+        result,
+        Replacement {
+            id: replacement_id,
+            evicted: vec![incumbent_id],
+        }
+    );
+    assert!(storage.contains(&replacement_id));
+    assert!(!storage.contains(&incumbent_id));
+}
@@ -4,6 +4,7 @@ use proptest::prelude::*;
 use proptest_derive::Arbitrary;
 
 use zebra_chain::{
+    amount::{Amount, NonNegative},
     at_least_one, orchard,
     primitives::Groth16Proof,
     sapling,
@@ -11,7 +12,7 @@ use zebra_chain::{
     transparent, LedgerState,
 };
 
-use super::super::{MempoolError, Storage};
+use super::super::{transaction_cost, MempoolError, Replacement, Storage};
 
 proptest! {
     #[test]
@@ -33,6 +34,10 @@ proptest! {
                 Ok(id_to_accept)
             );
 
+            // The conflicting spend(s) should already be identifiable before the rejected
+            // transaction is even attempted, regardless of which transaction version made them.
+            assert!(!storage.conflicting_ids(&transaction_to_reject).is_empty());
+
             assert_eq!(
                 storage.insert(transaction_to_reject),
                 Err(MempoolError::Rejected)
@@ -43,6 +48,78 @@ proptest! {
             storage.clear();
         }
     }
+
+    /// Every conflicting pair is also checked under both fee-rate permutations of
+    /// [`Storage::insert_with_replacement`]: a strictly lower fee than the incumbent's is
+    /// rejected without disturbing it, and a strictly higher fee replaces it.
+    ///
+    /// Fees are derived from each transaction's own [`transaction_cost`] rather than fixed
+    /// constants, so the fee-rate cross-multiplication in `replaces` comes out the same way
+    /// regardless of how large proptest happens to generate either transaction.
+    #[test]
+    fn conflicting_transactions_are_rejected_or_replaced_by_fee(input in any::<SpendConflictTestInput>()) {
+        let mut storage = Storage::default();
+
+        let (incumbent, conflict) = input.conflicting_transactions();
+        let incumbent_id = incumbent.id;
+
+        let incumbent_cost = transaction_cost(&incumbent) as i64;
+        let conflict_cost = transaction_cost(&conflict) as i64;
+
+        // A fee rate of exactly 1-per-cost-unit for the incumbent.
+        let incumbent_fee =
+            Amount::try_from(incumbent_cost).expect("transaction cost is non-negative");
+        // Strictly below the incumbent's absolute fee, so it's rejected regardless of cost.
+        let lower_fee =
+            Amount::try_from(incumbent_cost - 1).expect("transaction cost is at least 1");
+        // At least the incumbent's absolute fee, and - since it's spread over only
+        // `conflict_cost` rather than `incumbent_cost + conflict_cost` - strictly above the
+        // incumbent's fee rate too.
+        let higher_fee = Amount::try_from(incumbent_cost + conflict_cost)
+            .expect("transaction costs are non-negative");
+
+        storage.insert(with_conventional_fee(incumbent.clone(), incumbent_fee))
+            .expect("mempool is empty, so the incumbent has nothing to conflict with");
+
+        // A strictly lower fee than the incumbent's is rejected, and the incumbent survives.
+        let lower_fee_conflict = with_conventional_fee(conflict.clone(), lower_fee);
+        let lower_fee_conflict_id = lower_fee_conflict.id;
+
+        prop_assert_eq!(
+            storage.insert_with_replacement(lower_fee_conflict),
+            Err(MempoolError::Rejected)
+        );
+        prop_assert!(storage.contains_rejected(&lower_fee_conflict_id));
+        prop_assert!(storage.contains(&incumbent_id));
+
+        storage.clear();
+
+        storage.insert(with_conventional_fee(incumbent, incumbent_fee))
+            .expect("mempool is empty, so the incumbent has nothing to conflict with");
+
+        // A strictly higher fee than the incumbent's replaces it.
+        let higher_fee_conflict = with_conventional_fee(conflict, higher_fee);
+        let higher_fee_conflict_id = higher_fee_conflict.id;
+
+        prop_assert_eq!(
+            storage.insert_with_replacement(higher_fee_conflict),
+            Ok(Replacement {
+                id: higher_fee_conflict_id,
+                evicted: vec![incumbent_id],
+            })
+        );
+        prop_assert!(storage.contains(&higher_fee_conflict_id));
+        prop_assert!(!storage.contains(&incumbent_id));
+    }
+}
+
+/// Returns `tx` with its conventional fee overridden to `fee`, for deterministic replace-by-fee
+/// tests that don't depend on a real UTXO-based fee calculation.
+fn with_conventional_fee(tx: UnminedTx, fee: Amount<NonNegative>) -> UnminedTx {
+    UnminedTx {
+        conventional_fee: fee,
+        ..tx
+    }
 }
 
 #[derive(Arbitrary, Debug)]
@@ -66,6 +143,18 @@ enum SpendConflictTestInput {
 
         conflict: SpendConflictForTransactionV5,
     },
+
+    // A `V4` transaction and a `V5` transaction sharing a single conflicting transparent
+    // spend, so that cross-version conflicts are exercised as well as same-version ones.
+    MixedVersions {
+        #[proptest(strategy = "Transaction::v4_strategy(LedgerState::default())")]
+        first: Transaction,
+
+        #[proptest(strategy = "Transaction::v5_strategy(LedgerState::default())")]
+        second: Transaction,
+
+        conflict: TransparentSpendConflict,
+    },
 }
 
 impl SpendConflictTestInput {
@@ -89,6 +178,25 @@ impl SpendConflictTestInput {
                 conflict.clone().apply_to(&mut first);
                 conflict.apply_to(&mut second);
 
+                (first, second)
+            }
+            SpendConflictTestInput::MixedVersions {
+                mut first,
+                mut second,
+                conflict,
+            } => {
+                let first_inputs = match &mut first {
+                    Transaction::V4 { inputs, .. } => inputs,
+                    _ => unreachable!("incorrect transaction version generated for test"),
+                };
+                conflict.clone().apply_to(first_inputs);
+
+                let second_inputs = match &mut second {
+                    Transaction::V5 { inputs, .. } => inputs,
+                    _ => unreachable!("incorrect transaction version generated for test"),
+                };
+                conflict.apply_to(second_inputs);
+
                 (first, second)
             }
         };
@@ -126,11 +234,21 @@ struct SaplingSpendConflict<A: sapling::AnchorVariant + Clone> {
     new_spend: sapling::Spend<A>,
     new_shared_anchor: A::Shared,
     fallback_shielded_data: sapling::ShieldedData<A>,
+
+    /// An index into an existing spends list, used to pick which spend gets the conflicting
+    /// nullifier when there's more than one. Taken modulo the list's length, so any `usize` is
+    /// a valid value.
+    index: usize,
 }
 
 #[derive(Arbitrary, Clone, Debug)]
 struct OrchardSpendConflict {
     new_shielded_data: orchard::ShieldedData,
+
+    /// An index into an existing actions list, used to pick which action gets the conflicting
+    /// nullifier when there's more than one. Taken modulo the list's length, so any `usize` is
+    /// a valid value.
+    index: usize,
 }
 
 impl SpendConflictForTransactionV4 {
@@ -202,12 +320,13 @@ where
     type Parameters = ();
 
     fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-        any::<(sapling::Spend<A>, A::Shared, sapling::ShieldedData<A>)>()
-            .prop_map(|(new_spend, new_shared_anchor, fallback_shielded_data)| {
+        any::<(sapling::Spend<A>, A::Shared, sapling::ShieldedData<A>, usize)>()
+            .prop_map(|(new_spend, new_shared_anchor, fallback_shielded_data, index)| {
                 SaplingSpendConflict {
                     new_spend,
                     new_shared_anchor,
                     fallback_shielded_data,
+                    index,
                 }
             })
             .boxed()
@@ -223,7 +342,17 @@ impl<A: sapling::AnchorVariant + Clone> SaplingSpendConflict<A> {
         let shielded_data = sapling_shielded_data.get_or_insert(self.fallback_shielded_data);
 
         match &mut shielded_data.transfers {
-            SpendsAndMaybeOutputs { ref mut spends, .. } => spends.push(self.new_spend),
+            SpendsAndMaybeOutputs { ref mut spends, .. } => {
+                // Overwrite an arbitrary existing spend's nullifier, rather than always the
+                // first one, so that conflict detection is exercised regardless of where in the
+                // bundle the conflicting spend ends up.
+                let index = self.index % spends.iter().count();
+                spends
+                    .iter_mut()
+                    .nth(index)
+                    .expect("index is within bounds")
+                    .nullifier = self.new_spend.nullifier;
+            }
             JustOutputs { ref mut outputs } => {
                 let new_outputs = outputs.clone();
 
@@ -240,8 +369,28 @@ impl<A: sapling::AnchorVariant + Clone> SaplingSpendConflict<A> {
 impl OrchardSpendConflict {
     pub fn apply_to(self, orchard_shielded_data: &mut Option<orchard::ShieldedData>) {
         if let Some(shielded_data) = orchard_shielded_data.as_mut() {
-            shielded_data.actions.first_mut().action.nullifier =
-                self.new_shielded_data.actions.first().action.nullifier;
+            // Overwrite an arbitrary existing action's nullifier, rather than always the first
+            // one, so that conflict detection is exercised regardless of where in the bundle the
+            // conflicting action ends up.
+            let index = self.index % shielded_data.actions.iter().count();
+            let new_index = self.index % self.new_shielded_data.actions.iter().count();
+
+            let new_nullifier = self
+                .new_shielded_data
+                .actions
+                .iter()
+                .nth(new_index)
+                .expect("index is within bounds")
+                .action
+                .nullifier;
+
+            shielded_data
+                .actions
+                .iter_mut()
+                .nth(index)
+                .expect("index is within bounds")
+                .action
+                .nullifier = new_nullifier;
         } else {
             *orchard_shielded_data = Some(self.new_shielded_data);
         }
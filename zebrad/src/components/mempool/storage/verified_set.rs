@@ -0,0 +1,163 @@
+//! The set of currently verified mempool transactions, indexed for spend-conflict detection.
+
+use std::collections::{HashMap, HashSet};
+
+use zebra_chain::{
+    orchard, sapling, sprout,
+    transaction::{UnminedTx, UnminedTxId},
+    transparent,
+};
+
+/// A transparent outpoint or shielded nullifier spent by a transaction.
+///
+/// Every kind of spend a transaction can make - a transparent previous output, or a Sprout,
+/// Sapling, or Orchard nullifier - can conflict with the same kind of spend in another
+/// transaction, so [`VerifiedSet`] indexes all of them under this one key type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpendId {
+    /// A transparent previous output spent by a transparent input.
+    Transparent(transparent::OutPoint),
+    /// A Sprout nullifier revealed by a JoinSplit.
+    Sprout(sprout::Nullifier),
+    /// A Sapling nullifier revealed by a Sapling spend.
+    Sapling(sapling::Nullifier),
+    /// An Orchard nullifier revealed by an Orchard action.
+    Orchard(orchard::Nullifier),
+}
+
+/// The set of verified transactions currently held by the mempool, indexed by the spends they
+/// make so that a conflicting incoming transaction - and the transaction(s) it conflicts with -
+/// can be found without scanning every verified transaction.
+#[derive(Default)]
+pub struct VerifiedSet {
+    /// The verified transactions, in the order they were inserted.
+    transactions: Vec<UnminedTx>,
+
+    /// Every spend made by a transaction in [`Self::transactions`], mapped to the id of the
+    /// transaction that makes it.
+    spent_by: HashMap<SpendId, UnminedTxId>,
+}
+
+impl VerifiedSet {
+    /// Returns `true` if a transaction with `txid` is in this set.
+    pub fn contains(&self, txid: &UnminedTxId) -> bool {
+        self.transactions.iter().any(|tx| &tx.id == txid)
+    }
+
+    /// Returns the verified transaction with `txid`, if any.
+    pub fn transaction(&self, txid: &UnminedTxId) -> Option<&UnminedTx> {
+        self.transactions.iter().find(|tx| &tx.id == txid)
+    }
+
+    /// Returns `true` if `tx` conflicts with any transaction already in this set.
+    pub fn has_spend_conflicts(&self, tx: &UnminedTx) -> bool {
+        !self.conflicting_tx_ids(tx).is_empty()
+    }
+
+    /// Returns the ids of every transaction in this set that conflicts with `tx`: that spends at
+    /// least one of the same transparent outputs or shielded notes `tx` spends.
+    pub fn conflicting_tx_ids(&self, tx: &UnminedTx) -> HashSet<UnminedTxId> {
+        spend_ids(tx)
+            .filter_map(|spend_id| self.spent_by.get(&spend_id).copied())
+            .collect()
+    }
+
+    /// Returns every [`SpendId`] that `tx` spends which is already spent by a transaction in
+    /// this set.
+    pub fn conflicting_spend_ids(&self, tx: &UnminedTx) -> HashSet<SpendId> {
+        spend_ids(tx)
+            .filter(|spend_id| self.spent_by.contains_key(spend_id))
+            .collect()
+    }
+
+    /// Inserts `tx` into this set, indexing every spend it makes.
+    pub fn insert(&mut self, tx: UnminedTx) {
+        for spend_id in spend_ids(&tx) {
+            self.spent_by.insert(spend_id, tx.id);
+        }
+
+        self.transactions.push(tx);
+    }
+
+    /// Removes every transaction matching `predicate` from this set, along with their indexed
+    /// spends. Returns the number of transactions removed.
+    pub fn remove_all_that(&mut self, predicate: impl Fn(&UnminedTx) -> bool) -> usize {
+        let removed: Vec<UnminedTx> = {
+            let mut kept = Vec::with_capacity(self.transactions.len());
+            let mut removed = Vec::new();
+
+            for tx in self.transactions.drain(..) {
+                if predicate(&tx) {
+                    removed.push(tx);
+                } else {
+                    kept.push(tx);
+                }
+            }
+
+            self.transactions = kept;
+            removed
+        };
+
+        for tx in &removed {
+            for spend_id in spend_ids(tx) {
+                self.spent_by.remove(&spend_id);
+            }
+        }
+
+        removed.len()
+    }
+
+    /// Returns an iterator over every transaction in this set.
+    pub fn transactions(&self) -> impl Iterator<Item = &UnminedTx> {
+        self.transactions.iter()
+    }
+
+    /// Returns the number of transactions in this set.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns `true` if this set has no transactions.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Removes every transaction from this set.
+    pub fn clear(&mut self) {
+        self.transactions.clear();
+        self.spent_by.clear();
+    }
+}
+
+/// Returns every [`SpendId`] that `tx` spends, across its transparent inputs and its Sprout,
+/// Sapling, and Orchard shielded bundles.
+fn spend_ids(tx: &UnminedTx) -> impl Iterator<Item = SpendId> + '_ {
+    let transparent = tx
+        .transaction
+        .inputs()
+        .iter()
+        .filter_map(|input| match input {
+            transparent::Input::PrevOut { outpoint, .. } => Some(SpendId::Transparent(*outpoint)),
+            transparent::Input::Coinbase { .. } => None,
+        });
+
+    let sprout = tx
+        .transaction
+        .sprout_nullifiers()
+        .copied()
+        .map(SpendId::Sprout);
+
+    let sapling = tx
+        .transaction
+        .sapling_nullifiers()
+        .copied()
+        .map(SpendId::Sapling);
+
+    let orchard = tx
+        .transaction
+        .orchard_nullifiers()
+        .copied()
+        .map(SpendId::Orchard);
+
+    transparent.chain(sprout).chain(sapling).chain(orchard)
+}
@@ -2,7 +2,16 @@
 //!
 //! It is used when Zebra is a long way behind the current chain tip.
 
-use std::{collections::HashSet, pin::Pin, sync::Arc, task::Poll, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
 use color_eyre::eyre::{eyre, Report};
 use futures::stream::{FuturesUnordered, StreamExt};
@@ -16,7 +25,7 @@ use tower::{
 use zebra_chain::{
     block::{self, Block},
     chain_tip::ChainTip,
-    parameters::genesis_hash,
+    parameters::{genesis_hash, Network},
 };
 use zebra_consensus::{
     chain::VerifyChainError, BlockError, VerifyBlockError, VerifyCheckpointError,
@@ -30,6 +39,7 @@ use crate::{
 
 mod downloads;
 mod gossip;
+mod progress;
 mod recent_sync_lengths;
 mod status;
 
@@ -37,8 +47,10 @@ mod status;
 mod tests;
 
 use downloads::{AlwaysHedge, Downloads};
+use progress::ProgressTracker;
 
 pub use gossip::{gossip_best_tip_block_hashes, BlockGossipError};
+pub use progress::SyncProgress;
 pub use recent_sync_lengths::RecentSyncLengths;
 pub use status::SyncStatus;
 
@@ -177,6 +189,18 @@ const SYNC_RESTART_DELAY: Duration = Duration::from_secs(67);
 /// a denial of service on those peers.
 const GENESIS_TIMEOUT_RETRY: Duration = Duration::from_secs(5);
 
+/// The number of consecutive successful block commits needed before the additive-increase step
+/// raises [`ChainSync::effective_lookahead_limit`] by one.
+///
+/// Small enough to ramp up quickly on a fast, reliable link, but large enough that a handful of
+/// successes right after a multiplicative decrease don't immediately cancel it out.
+const LOOKAHEAD_INCREASE_INTERVAL: usize = 10;
+
+/// The multiplicative decrease factor applied to [`ChainSync::effective_lookahead_limit`]
+/// whenever the syncer hits an error that would restart the sync, or a block download or verify
+/// timeout fires.
+const LOOKAHEAD_DECREASE_FACTOR: f64 = 0.5;
+
 /// Helps work around defects in the bitcoin protocol by checking whether
 /// the returned hashes actually extend a chain tip.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -208,12 +232,33 @@ where
     ZSTip: ChainTip + Clone + Send + 'static,
 {
     // Configuration
+    /// The configured network, used to estimate the network chain tip height for
+    /// [`SyncProgress::estimated_tip_height`].
+    network: Network,
+
     /// The genesis hash for the configured network
     genesis_hash: block::Hash,
 
     /// The configured lookahead limit, after applying the minimum limit.
+    ///
+    /// This is the ceiling for [`Self::effective_lookahead_limit`], not the value the sync loop
+    /// actually throttles on.
     lookahead_limit: usize,
 
+    /// The current, adaptively-tuned lookahead limit that the sync loop actually throttles on.
+    ///
+    /// Starts at [`MIN_LOOKAHEAD_LIMIT`]. An additive-increase/multiplicative-decrease scheme
+    /// (similar to TCP congestion control) grows it by one every
+    /// [`LOOKAHEAD_INCREASE_INTERVAL`] consecutive successful block commits, and shrinks it by
+    /// [`LOOKAHEAD_DECREASE_FACTOR`] whenever [`Self::should_restart_sync`] returns `true` for a
+    /// download or verify error, including [`BLOCK_DOWNLOAD_TIMEOUT`] and
+    /// [`BLOCK_VERIFY_TIMEOUT`] firing. Always within `MIN_LOOKAHEAD_LIMIT..=lookahead_limit`.
+    effective_lookahead_limit: usize,
+
+    /// The number of consecutive successful block commits since the last additive increase of
+    /// [`Self::effective_lookahead_limit`].
+    lookahead_successes: usize,
+
     // Services
     /// A network service which is used to perform ObtainTips and ExtendTips
     /// requests.
@@ -242,6 +287,35 @@ where
     /// The cached block chain state.
     state: ZS,
 
+    /// The latest chain tip, used to read the verified height and estimate the network chain
+    /// tip height for [`SyncProgress`] updates.
+    latest_chain_tip: ZSTip,
+
+    /// Tracks recent commit timestamps and broadcasts [`SyncProgress`] snapshots.
+    progress: ProgressTracker,
+
+    /// The number of recent `NotFound` download failures, within [`Self::not_found_restart_window`],
+    /// that triggers a sync restart.
+    ///
+    /// See [`Self::record_not_found_failure`].
+    not_found_restart_threshold: usize,
+
+    /// The time window over which recent `NotFound` download failures are counted towards
+    /// [`Self::not_found_restart_threshold`].
+    not_found_restart_window: Duration,
+
+    /// Timestamps of recent `NotFound` download failures, within [`Self::not_found_restart_window`].
+    not_found_failures: VecDeque<Instant>,
+
+    /// The path of the on-disk sync checkpoint file, if persistence is enabled.
+    ///
+    /// `None` both disables persistence and acts as the config's enable/disable flag: there's no
+    /// separate boolean, since a path to persist to is all the information persistence needs.
+    /// When set, [`Self::prospective_tips`] is written to this path whenever it changes, and
+    /// reloaded from it once, on [`Self::sync`] startup, so a crash or restart doesn't force a
+    /// full re-scan from the genesis block locator.
+    checkpoint_path: Option<PathBuf>,
+
     // Internal sync state
     /// The tips that the syncer is currently following.
     prospective_tips: HashSet<CheckedTip>,
@@ -285,14 +359,15 @@ where
     ///  - state: the zebra-state that stores the chain
     ///  - latest_chain_tip: the latest chain tip from `state`
     ///
-    /// Also returns a [`SyncStatus`] to check if the syncer has likely reached the chain tip.
+    /// Also returns a [`SyncStatus`] to check if the syncer has likely reached the chain tip,
+    /// and a [`watch::Receiver`](tokio::sync::watch::Receiver) of [`SyncProgress`] snapshots.
     pub fn new(
         config: &ZebradConfig,
         peers: ZN,
         verifier: ZV,
         state: ZS,
         latest_chain_tip: ZSTip,
-    ) -> (Self, SyncStatus) {
+    ) -> (Self, SyncStatus, tokio::sync::watch::Receiver<SyncProgress>) {
         let tip_network = Timeout::new(peers.clone(), TIPS_RESPONSE_TIMEOUT);
         // The Hedge middleware is the outermost layer, hedging requests
         // between two retry-wrapped networks.  The innermost timeout
@@ -338,23 +413,33 @@ where
         );
 
         let (sync_status, recent_syncs) = SyncStatus::new();
+        let (progress, progress_receiver) = ProgressTracker::new();
 
         let new_syncer = Self {
+            network: config.network.network,
             genesis_hash: genesis_hash(config.network.network),
             lookahead_limit: config.sync.lookahead_limit,
+            effective_lookahead_limit: MIN_LOOKAHEAD_LIMIT,
+            lookahead_successes: 0,
             tip_network,
             downloads: Box::pin(Downloads::new(
                 block_network,
                 verifier,
-                latest_chain_tip,
+                latest_chain_tip.clone(),
                 config.sync.lookahead_limit,
             )),
             state,
+            latest_chain_tip,
+            progress,
+            not_found_restart_threshold: config.sync.not_found_restart_threshold,
+            not_found_restart_window: config.sync.not_found_restart_window,
+            not_found_failures: VecDeque::new(),
+            checkpoint_path: config.sync.checkpoint_path.clone(),
             prospective_tips: HashSet::new(),
             recent_syncs,
         };
 
-        (new_syncer, sync_status)
+        (new_syncer, sync_status, progress_receiver)
     }
 
     #[instrument(skip(self))]
@@ -363,6 +448,10 @@ where
         // due to protocol limitations
         self.request_genesis().await?;
 
+        // Restore any persisted prospective tips, so a restart after a crash doesn't force a
+        // full re-scan from the genesis block locator.
+        self.load_checkpoint().await;
+
         // Distinguishes a restart from a start, so we don't sleep when starting
         // the sync process, but we can keep restart logic in one place.
         let mut started_once = false;
@@ -371,19 +460,29 @@ where
             if started_once {
                 tracing::info!(timeout = ?SYNC_RESTART_DELAY, "waiting to restart sync");
                 self.prospective_tips = HashSet::new();
+                self.not_found_failures.clear();
                 self.downloads.cancel_all();
                 self.update_metrics();
+                self.save_checkpoint();
                 sleep(SYNC_RESTART_DELAY).await;
             } else {
                 started_once = true;
             }
 
-            tracing::info!("starting sync, obtaining new tips");
-            if let Err(e) = self.obtain_tips().await {
-                tracing::warn!(?e, "error obtaining tips");
-                continue 'sync;
+            if self.prospective_tips.is_empty() {
+                tracing::info!("starting sync, obtaining new tips");
+                if let Err(e) = self.obtain_tips().await {
+                    tracing::warn!(?e, "error obtaining tips");
+                    continue 'sync;
+                }
+            } else {
+                tracing::info!(
+                    tips.len = self.prospective_tips.len(),
+                    "resuming sync from persisted checkpoint"
+                );
             }
             self.update_metrics();
+            self.save_checkpoint();
 
             while !self.prospective_tips.is_empty() {
                 // Check whether any block tasks are currently ready:
@@ -391,9 +490,13 @@ where
                     match rsp {
                         Ok(hash) => {
                             tracing::trace!(?hash, "verified and committed block to state");
+                            self.record_lookahead_success();
+                            self.save_checkpoint();
+                            self.record_progress();
                         }
                         Err(e) => {
-                            if Self::should_restart_sync(e) {
+                            if self.should_restart_sync(e) {
+                                self.record_lookahead_failure();
                                 continue 'sync;
                             }
                         }
@@ -405,29 +508,33 @@ where
                 //
                 // Starting to wait is interesting, but logging each wait can be
                 // very verbose.
-                if self.downloads.in_flight() > self.lookahead_limit {
+                if self.downloads.in_flight() > self.effective_lookahead_limit {
                     tracing::info!(
                         tips.len = self.prospective_tips.len(),
                         in_flight = self.downloads.in_flight(),
-                        lookahead_limit = self.lookahead_limit,
+                        lookahead_limit = self.effective_lookahead_limit,
                         "waiting for pending blocks",
                     );
                 }
-                while self.downloads.in_flight() > self.lookahead_limit {
+                while self.downloads.in_flight() > self.effective_lookahead_limit {
                     tracing::trace!(
                         tips.len = self.prospective_tips.len(),
                         in_flight = self.downloads.in_flight(),
-                        lookahead_limit = self.lookahead_limit,
+                        lookahead_limit = self.effective_lookahead_limit,
                         "waiting for pending blocks",
                     );
 
                     match self.downloads.next().await.expect("downloads is nonempty") {
                         Ok(hash) => {
                             tracing::trace!(?hash, "verified and committed block to state");
+                            self.record_lookahead_success();
+                            self.save_checkpoint();
+                            self.record_progress();
                         }
 
                         Err(e) => {
-                            if Self::should_restart_sync(e) {
+                            if self.should_restart_sync(e) {
+                                self.record_lookahead_failure();
                                 continue 'sync;
                             }
                         }
@@ -439,7 +546,7 @@ where
                 tracing::info!(
                     tips.len = self.prospective_tips.len(),
                     in_flight = self.downloads.in_flight(),
-                    lookahead_limit = self.lookahead_limit,
+                    lookahead_limit = self.effective_lookahead_limit,
                     "extending tips",
                 );
 
@@ -448,6 +555,7 @@ where
                     continue 'sync;
                 }
                 self.update_metrics();
+                self.save_checkpoint();
             }
 
             tracing::info!("exhausted prospective tip set");
@@ -494,7 +602,15 @@ where
             )));
         }
 
-        let mut download_set = IndexSet::new();
+        // The hash chain reported by each peer that responded, collected so that the final
+        // download order can be reconstructed from majority agreement, instead of from
+        // whichever peer happened to respond first.
+        let mut peer_hash_chains = Vec::new();
+
+        // The `expected_next` hashes of new tips seen so far this round, so that responses
+        // agreeing on the same tip don't each redundantly update `prospective_tips`.
+        let mut seen_tips_this_round = HashSet::new();
+
         while let Some(res) = requests.next().await {
             match res
                 .expect("panic in spawned obtain tips request")
@@ -549,7 +665,7 @@ where
 
                     // Make sure we get the same tips, regardless of the
                     // order of peer responses
-                    if !download_set.contains(&new_tip.expected_next) {
+                    if seen_tips_this_round.insert(new_tip.expected_next) {
                         tracing::debug!(?new_tip,
                                         "adding new prospective tip, and removing existing tips in the new block hash list");
                         self.prospective_tips
@@ -562,15 +678,15 @@ where
                         );
                     }
 
-                    // security: the first response determines our download order
-                    //
-                    // TODO: can we make the download order independent of response order?
-                    let prev_download_len = download_set.len();
-                    download_set.extend(unknown_hashes);
-                    let new_download_len = download_set.len();
-                    let new_hashes = new_download_len - prev_download_len;
-                    tracing::debug!(new_hashes, "added hashes to download set");
-                    metrics::histogram!("sync.obtain.response.hash.count", new_hashes as f64);
+                    tracing::debug!(
+                        unknown_hashes.len = unknown_hashes.len(),
+                        "collected hash chain from peer response"
+                    );
+                    metrics::histogram!(
+                        "sync.obtain.response.hash.count",
+                        unknown_hashes.len() as f64
+                    );
+                    peer_hash_chains.push(unknown_hashes.to_vec());
                 }
                 Ok(_) => unreachable!("network returned wrong response"),
                 // We ignore this error because we made multiple fanout requests.
@@ -578,6 +694,10 @@ where
             }
         }
 
+        // security: reconstruct the download order from majority agreement between peer
+        // responses, so a single fastest-responding peer can't dictate our download order.
+        let download_set = merge_peer_hash_chains(peer_hash_chains);
+
         tracing::debug!(?self.prospective_tips);
 
         // Check that the new tips we got are actually unknown.
@@ -626,6 +746,16 @@ where
                     },
                 )));
             }
+
+            // The hash chain reported by each peer that responded to this tip's FindBlocks
+            // fanout, collected so that the final download order can be reconstructed from
+            // majority agreement, instead of from whichever peer happened to respond first.
+            let mut peer_hash_chains = Vec::new();
+
+            // The `expected_next` hashes of new tips seen so far for this tip's fanout, so that
+            // responses agreeing on the same tip don't each redundantly update `prospective_tips`.
+            let mut seen_tips_this_fanout = HashSet::new();
+
             while let Some(res) = responses.next().await {
                 match res
                     .expect("panic in spawned extend tips request")
@@ -695,7 +825,7 @@ where
 
                         // Make sure we get the same tips, regardless of the
                         // order of peer responses
-                        if !download_set.contains(&new_tip.expected_next) {
+                        if seen_tips_this_fanout.insert(new_tip.expected_next) {
                             tracing::debug!(?new_tip,
                                             "adding new prospective tip, and removing any existing tips in the new block hash list");
                             self.prospective_tips
@@ -708,21 +838,25 @@ where
                             );
                         }
 
-                        // security: the first response determines our download order
-                        //
-                        // TODO: can we make the download order independent of response order?
-                        let prev_download_len = download_set.len();
-                        download_set.extend(unknown_hashes);
-                        let new_download_len = download_set.len();
-                        let new_hashes = new_download_len - prev_download_len;
-                        tracing::debug!(new_hashes, "added hashes to download set");
-                        metrics::histogram!("sync.extend.response.hash.count", new_hashes as f64);
+                        tracing::debug!(
+                            unknown_hashes.len = unknown_hashes.len(),
+                            "collected hash chain from peer response"
+                        );
+                        metrics::histogram!(
+                            "sync.extend.response.hash.count",
+                            unknown_hashes.len() as f64
+                        );
+                        peer_hash_chains.push(unknown_hashes.to_vec());
                     }
                     Ok(_) => unreachable!("network returned wrong response"),
                     // We ignore this error because we made multiple fanout requests.
                     Err(e) => tracing::debug!(?e),
                 }
             }
+
+            // security: reconstruct the download order from majority agreement between peer
+            // responses, so a single fastest-responding peer can't dictate our download order.
+            download_set.extend(merge_peer_hash_chains(peer_hash_chains));
         }
 
         let new_downloads = download_set.len();
@@ -781,6 +915,13 @@ where
     ///
     /// BUG: check if the hash is in any chain (#862)
     /// Depth only checks the main chain.
+    ///
+    /// TODO (#862): once `zebra-state` exposes a request that reports whether a hash is known to
+    /// *any* chain, not just the best chain - for example a `Request::KnownBlock` returning a
+    /// `Response::KnownBlock(Option<BlockLocation>)` - switch the re-check loops in
+    /// `obtain_tips`/`extend_tips` to call that instead of this method. Until then, a block we've
+    /// already downloaded and verified into a side chain during a reorg looks unknown here, so
+    /// it gets redundantly re-queued for download and re-verification.
     async fn state_contains(&mut self, hash: block::Hash) -> Result<bool, Report> {
         match self
             .state
@@ -797,6 +938,83 @@ where
         }
     }
 
+    /// Restores [`Self::prospective_tips`] from [`Self::checkpoint_path`], if persistence is
+    /// enabled and a checkpoint file exists.
+    ///
+    /// Any persisted tip that the state has already caught up to is discarded, since replaying
+    /// it would just be a stale hint: the syncer has moved past it since the checkpoint was
+    /// written.
+    ///
+    /// A missing or corrupt checkpoint file is not an error: it just means we fall back to the
+    /// normal `obtain_tips` startup path.
+    async fn load_checkpoint(&mut self) {
+        let Some(path) = self.checkpoint_path.clone() else {
+            return;
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                tracing::debug!(?error, ?path, "no persisted sync checkpoint to load");
+                return;
+            }
+        };
+
+        let mut restored_tips = HashSet::new();
+        for line in contents.lines() {
+            let Some((tip, expected_next)) = line.split_once(' ') else {
+                tracing::warn!(?path, ?line, "ignoring malformed sync checkpoint line");
+                continue;
+            };
+
+            let tip = block::Hash::from_str(tip);
+            let expected_next = block::Hash::from_str(expected_next);
+            let (Ok(tip), Ok(expected_next)) = (tip, expected_next) else {
+                tracing::warn!(?path, ?line, "ignoring malformed sync checkpoint line");
+                continue;
+            };
+
+            match self.state_contains(tip).await {
+                Ok(true) => continue,
+                Ok(false) => restored_tips.insert(CheckedTip { tip, expected_next }),
+                Err(error) => {
+                    tracing::warn!(?error, "failed to validate persisted sync checkpoint tip");
+                    continue;
+                }
+            };
+        }
+
+        if !restored_tips.is_empty() {
+            tracing::info!(
+                tips.len = restored_tips.len(),
+                ?path,
+                "restored sync checkpoint"
+            );
+            self.prospective_tips = restored_tips;
+        }
+    }
+
+    /// Persists [`Self::prospective_tips`] to [`Self::checkpoint_path`], if persistence is
+    /// enabled.
+    ///
+    /// Errors are logged rather than propagated: a failed checkpoint write is never a reason to
+    /// stop syncing, since the checkpoint is only a startup optimization.
+    fn save_checkpoint(&self) {
+        let Some(path) = &self.checkpoint_path else {
+            return;
+        };
+
+        let contents: String = self
+            .prospective_tips
+            .iter()
+            .map(|checked_tip| format!("{} {}\n", checked_tip.tip, checked_tip.expected_next))
+            .collect();
+
+        if let Err(error) = fs::write(path, contents) {
+            tracing::warn!(?error, ?path, "failed to persist sync checkpoint");
+        }
+    }
+
     fn update_metrics(&mut self) {
         metrics::gauge!(
             "sync.prospective_tips.len",
@@ -806,11 +1024,66 @@ where
             "sync.downloads.in_flight",
             self.downloads.in_flight() as f64
         );
+        metrics::gauge!(
+            "sync.downloads.lookahead_limit",
+            self.effective_lookahead_limit as f64
+        );
+    }
+
+    /// Broadcasts an updated [`SyncProgress`] after a block has been committed.
+    ///
+    /// Does nothing if [`Self::latest_chain_tip`] doesn't have a height yet, which shouldn't
+    /// happen here, since we've just committed a block, but is handled defensively rather than
+    /// asserted, since progress reporting should never be able to panic the syncer.
+    fn record_progress(&mut self) {
+        if let Some(verified_height) = self.latest_chain_tip.best_tip_height() {
+            let estimated_tip_height = self
+                .latest_chain_tip
+                .estimate_network_chain_tip_height(self.network);
+
+            self.progress.record_commit(
+                verified_height,
+                estimated_tip_height,
+                self.downloads.in_flight(),
+            );
+        }
+    }
+
+    /// Additively increases [`Self::effective_lookahead_limit`] after a successful block commit,
+    /// once every [`LOOKAHEAD_INCREASE_INTERVAL`] consecutive successes.
+    fn record_lookahead_success(&mut self) {
+        self.lookahead_successes += 1;
+
+        if self.lookahead_successes >= LOOKAHEAD_INCREASE_INTERVAL {
+            self.lookahead_successes = 0;
+            self.effective_lookahead_limit =
+                (self.effective_lookahead_limit + 1).min(self.lookahead_limit);
+        }
+    }
+
+    /// Multiplicatively decreases [`Self::effective_lookahead_limit`] after a sync restart or a
+    /// block download/verify timeout, clamped to [`MIN_LOOKAHEAD_LIMIT`].
+    fn record_lookahead_failure(&mut self) {
+        self.lookahead_successes = 0;
+        self.effective_lookahead_limit = ((self.effective_lookahead_limit as f64
+            * LOOKAHEAD_DECREASE_FACTOR) as usize)
+            .max(MIN_LOOKAHEAD_LIMIT);
     }
 
     /// Return if the sync should be restarted based on the given error
     /// from the block downloader and verifier stream.
-    fn should_restart_sync(e: BlockDownloadVerifyError) -> bool {
+    ///
+    /// TODO (#2908): the `Commit` and `DownloadFailed` arms below still classify by
+    /// stringifying the inner error and matching on substrings, rather than by a typed
+    /// `CommitError::AlreadyCommitted` variant and a typed `DownloadFailed` failure kind. That
+    /// refactor needs matching changes in the commit error type (in `zebra-state`/
+    /// `zebra-consensus`) and in this crate's own `downloads` module's `BlockDownloadVerifyError`,
+    /// neither of which are present in this checkout to extend - see the `downloads` module
+    /// declaration at the top of this file, which currently has no backing file. Once those types exist here with
+    /// typed variants, replace the two string-matched arms with structural matches, and delete
+    /// the best-effort downcast-mismatch check in the `_` arm below, which exists only to detect
+    /// drift between the stringly-typed arms and the real error shapes.
+    fn should_restart_sync(&mut self, e: BlockDownloadVerifyError) -> bool {
         match e {
             // Structural matches
             BlockDownloadVerifyError::Invalid(VerifyChainError::Checkpoint(
@@ -852,10 +1125,19 @@ where
             BlockDownloadVerifyError::DownloadFailed(ref source)
                 if format!("{:?}", source).contains("NotFound") =>
             {
-                // TODO: improve this by checking the type (#2908)
-                //       restart after a certain number of NotFound errors?
-                tracing::debug!(error = ?e, "block was not found, possibly from a peer that doesn't have the block yet, continuing");
-                false
+                if self.record_not_found_failure() {
+                    tracing::warn!(
+                        error = ?e,
+                        threshold = self.not_found_restart_threshold,
+                        window = ?self.not_found_restart_window,
+                        "reached the configured NotFound failure threshold, \
+                         restarting sync to find fresh peers"
+                    );
+                    true
+                } else {
+                    tracing::debug!(error = ?e, "block was not found, possibly from a peer that doesn't have the block yet, continuing");
+                    false
+                }
             }
 
             _ => {
@@ -887,4 +1169,69 @@ where
             }
         }
     }
+
+    /// Records a `NotFound` download failure, and returns `true` if the number of such failures
+    /// within [`Self::not_found_restart_window`] has now reached
+    /// [`Self::not_found_restart_threshold`].
+    ///
+    /// Many peers responding `NotFound` for blocks near our prospective tip usually means we've
+    /// latched onto a fork the rest of the network has abandoned. Restarting clears
+    /// [`Self::prospective_tips`] and re-runs `obtain_tips`, which picks fresh prospective tips,
+    /// possibly from different peers.
+    fn record_not_found_failure(&mut self) -> bool {
+        let now = Instant::now();
+        self.not_found_failures.push_back(now);
+
+        let window_start = now - self.not_found_restart_window;
+        while matches!(self.not_found_failures.front(), Some(time) if *time < window_start) {
+            self.not_found_failures.pop_front();
+        }
+
+        metrics::gauge!(
+            "sync.not_found_failures.streak",
+            self.not_found_failures.len() as f64
+        );
+
+        self.not_found_failures.len() >= self.not_found_restart_threshold
+    }
+}
+
+/// Merges multiple peers' hash chains (each already in chain order, extending from the same
+/// starting point) into a single, deterministic download order.
+///
+/// A hash at position `i` is included if a strict majority of the chains that are at least
+/// `i + 1` hashes long agree on it; the merged chain stops at the first position without such a
+/// majority. This reconstructs the longest chain prefix that the majority of responding peers
+/// agree on, so a single fastest-responding peer can't unilaterally determine the order blocks
+/// are downloaded in.
+fn merge_peer_hash_chains(chains: Vec<Vec<block::Hash>>) -> IndexSet<block::Hash> {
+    let mut merged = IndexSet::new();
+
+    let max_len = chains.iter().map(Vec::len).max().unwrap_or(0);
+
+    for i in 0..max_len {
+        let mut hash_counts: HashMap<block::Hash, usize> = HashMap::new();
+        let mut considered = 0;
+
+        for chain in &chains {
+            if let Some(hash) = chain.get(i) {
+                considered += 1;
+                *hash_counts.entry(*hash).or_insert(0) += 1;
+            }
+        }
+
+        let majority_hash = hash_counts
+            .into_iter()
+            .find(|(_, count)| count * 2 > considered)
+            .map(|(hash, _)| hash);
+
+        match majority_hash {
+            Some(hash) => {
+                merged.insert(hash);
+            }
+            None => break,
+        }
+    }
+
+    merged
 }
@@ -0,0 +1,115 @@
+//! Structured sync progress reporting.
+//!
+//! [`SyncStatus`](super::SyncStatus) only answers "are we close to the tip?". This module adds
+//! a richer [`SyncProgress`] snapshot - verified height, estimated target height, in-flight
+//! downloads, and a derived rate/ETA - broadcast on a [`watch`] channel so callers like the RPC
+//! layer or a terminal UI can display live progress without scraping logs.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
+
+use zebra_chain::block;
+
+/// The number of recent block-commit timestamps kept for computing
+/// [`SyncProgress::blocks_per_second`].
+///
+/// Bounded so the hot commit path stays cheap: each commit is an O(1) push/pop into a ring
+/// buffer of this size, not an unbounded log of every block ever committed.
+const COMMIT_RATE_WINDOW: usize = 64;
+
+/// A snapshot of sync progress, recomputed and broadcast on every committed block.
+#[derive(Clone, Debug, Default)]
+pub struct SyncProgress {
+    /// The height of the most recently committed block, if any block has been committed yet.
+    pub verified_height: Option<block::Height>,
+
+    /// The estimated network chain tip height, from
+    /// [`ChainTip::estimate_network_chain_tip_height`](zebra_chain::chain_tip::ChainTip::estimate_network_chain_tip_height)
+    /// on the local best tip.
+    pub estimated_tip_height: Option<block::Height>,
+
+    /// The number of blocks currently downloading or verifying.
+    pub in_flight: usize,
+
+    /// Recent blocks committed per second, computed over a sliding window of commit timestamps.
+    pub blocks_per_second: f64,
+}
+
+impl SyncProgress {
+    /// Returns the estimated time remaining to reach [`Self::estimated_tip_height`].
+    ///
+    /// Returns `None` if the verified or estimated height is unknown, if we've already reached
+    /// the estimated tip, or if the recent commit rate is zero (so a remaining-time estimate
+    /// would be infinite or meaningless).
+    pub fn eta(&self) -> Option<Duration> {
+        let verified_height = self.verified_height?;
+        let estimated_tip_height = self.estimated_tip_height?;
+
+        let remaining_blocks = estimated_tip_height - verified_height;
+
+        if self.blocks_per_second <= 0.0 || remaining_blocks <= 0 {
+            return None;
+        }
+
+        let remaining_blocks = remaining_blocks as f64;
+
+        Some(Duration::from_secs_f64(
+            remaining_blocks / self.blocks_per_second,
+        ))
+    }
+}
+
+/// Tracks recent commit timestamps and broadcasts [`SyncProgress`] snapshots to subscribers.
+pub struct ProgressTracker {
+    sender: watch::Sender<SyncProgress>,
+    commit_times: VecDeque<Instant>,
+}
+
+impl ProgressTracker {
+    /// Returns a new tracker, and the [`watch::Receiver`] that observes its updates.
+    pub fn new() -> (Self, watch::Receiver<SyncProgress>) {
+        let (sender, receiver) = watch::channel(SyncProgress::default());
+
+        let tracker = ProgressTracker {
+            sender,
+            commit_times: VecDeque::with_capacity(COMMIT_RATE_WINDOW),
+        };
+
+        (tracker, receiver)
+    }
+
+    /// Records that `verified_height` has just been committed, and broadcasts an updated
+    /// [`SyncProgress`].
+    pub fn record_commit(
+        &mut self,
+        verified_height: block::Height,
+        estimated_tip_height: Option<block::Height>,
+        in_flight: usize,
+    ) {
+        let now = Instant::now();
+        self.commit_times.push_back(now);
+        if self.commit_times.len() > COMMIT_RATE_WINDOW {
+            self.commit_times.pop_front();
+        }
+
+        let blocks_per_second = match (self.commit_times.front(), self.commit_times.back()) {
+            (Some(first), Some(last)) if self.commit_times.len() > 1 && last > first => {
+                (self.commit_times.len() - 1) as f64 / (*last - *first).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+
+        // The receiver may have been dropped if nothing is watching; `send` only fails in that
+        // case, and there's nothing useful to do about it, so the result is ignored.
+        let _ = self.sender.send(SyncProgress {
+            verified_height: Some(verified_height),
+            estimated_tip_height,
+            in_flight,
+            blocks_per_second,
+        });
+    }
+}
@@ -0,0 +1,66 @@
+//! Tests for the chain synchronizer.
+
+use zebra_chain::block;
+
+use super::merge_peer_hash_chains;
+
+/// Returns a fake, distinct [`block::Hash`] for each `byte`.
+fn fake_hash(byte: u8) -> block::Hash {
+    block::Hash([byte; 32])
+}
+
+#[test]
+fn merge_peer_hash_chains_agrees_regardless_of_response_order() {
+    let hash_a = fake_hash(1);
+    let hash_b = fake_hash(2);
+    let hash_c = fake_hash(3);
+
+    // Every permutation of arrival order for the same set of peer responses.
+    let in_order = vec![
+        vec![hash_a, hash_b, hash_c],
+        vec![hash_a, hash_b, hash_c],
+        vec![hash_a, hash_b],
+    ];
+    let reordered = vec![
+        vec![hash_a, hash_b],
+        vec![hash_a, hash_b, hash_c],
+        vec![hash_a, hash_b, hash_c],
+    ];
+    let reversed = vec![
+        vec![hash_a, hash_b, hash_c],
+        vec![hash_a, hash_b],
+        vec![hash_a, hash_b, hash_c],
+    ];
+
+    let in_order_merged = merge_peer_hash_chains(in_order);
+    let reordered_merged = merge_peer_hash_chains(reordered);
+    let reversed_merged = merge_peer_hash_chains(reversed);
+
+    // Two out of three responses agree on `hash_c`, so it's included.
+    let expected: Vec<_> = vec![hash_a, hash_b, hash_c];
+
+    assert_eq!(in_order_merged.into_iter().collect::<Vec<_>>(), expected);
+    assert_eq!(reordered_merged.into_iter().collect::<Vec<_>>(), expected);
+    assert_eq!(reversed_merged.into_iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn merge_peer_hash_chains_stops_at_first_disagreement() {
+    let hash_a = fake_hash(1);
+    let hash_b = fake_hash(2);
+    let hash_other = fake_hash(9);
+
+    let chains = vec![vec![hash_a, hash_b], vec![hash_a, hash_other]];
+
+    let merged = merge_peer_hash_chains(chains);
+
+    // Neither `hash_b` nor `hash_other` has a majority, so the merge stops after `hash_a`.
+    assert_eq!(merged.into_iter().collect::<Vec<_>>(), vec![hash_a]);
+}
+
+#[test]
+fn merge_peer_hash_chains_empty_input_is_empty() {
+    let merged = merge_peer_hash_chains(vec![]);
+
+    assert!(merged.is_empty());
+}
@@ -0,0 +1,177 @@
+//! A small supervisor for long-running background tasks that should be restarted in place on
+//! failure, rather than bringing down the whole node.
+//!
+//! `StartCmd::start` uses this for ongoing tasks whose failures are usually transient (a peer
+//! response timeout, a momentarily unavailable service) and shouldn't require a full zebrad
+//! restart to recover from.
+
+use std::{future::Future, time::Duration};
+
+use color_eyre::eyre::Report;
+
+/// How many times - and how long to wait between attempts - a supervised task may be restarted
+/// before its failure is treated as fatal.
+#[derive(Clone, Copy, Debug)]
+pub struct RestartPolicy {
+    /// The maximum number of times to restart the task after it exits with an error.
+    pub max_restarts: u32,
+
+    /// How long to wait before restarting the task, after it exits with an error.
+    pub backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// A restart policy for Zebra's long-running background tasks: a handful of retries, spaced
+    /// out enough that a restart loop doesn't itself become a denial-of-service against whatever
+    /// the task depends on.
+    pub const DEFAULT: RestartPolicy = RestartPolicy {
+        max_restarts: 3,
+        backoff: Duration::from_secs(5),
+    };
+}
+
+/// Runs the future returned by `spawn` in a loop, restarting it whenever it exits with an error,
+/// until it has been restarted `policy.max_restarts` times.
+///
+/// `task_name` is used only for logging. Returns `Ok(())` if the task eventually exits
+/// successfully. Returns the last `Err` once the restart budget is exhausted - at that point the
+/// caller should treat the failure the same way it would an unsupervised task exiting with an
+/// error, because [`supervise`] has already used up its retries trying to recover in place.
+///
+/// TODO: this treats every error as restartable. Distinguishing fatal from transient failures
+/// would mean threading a `fatal()` classifier per task through its own error type, which none of
+/// the currently-supervised tasks define yet.
+pub async fn supervise<F, Fut>(task_name: &'static str, policy: RestartPolicy, spawn: F) -> Result<(), Report>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), Report>>,
+{
+    let mut restarts = 0;
+
+    loop {
+        match spawn().await {
+            Ok(()) => return Ok(()),
+            Err(err) if restarts < policy.max_restarts => {
+                restarts += 1;
+
+                tracing::warn!(
+                    task = task_name,
+                    restarts,
+                    max_restarts = policy.max_restarts,
+                    %err,
+                    "supervised task exited, restarting after backoff",
+                );
+
+                tokio::time::sleep(policy.backoff).await;
+            }
+            Err(err) => {
+                tracing::error!(
+                    task = task_name,
+                    %err,
+                    "supervised task exceeded its restart budget, treating as fatal",
+                );
+
+                return Err(err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// A task that fails `failures_before_success` times, then succeeds, recording every attempt
+    /// in `attempts`.
+    fn flaky_task(
+        attempts: Arc<AtomicU32>,
+        failures_before_success: u32,
+    ) -> impl Fn() -> futures::future::Ready<Result<(), Report>> {
+        move || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+
+            let result = if attempt < failures_before_success {
+                Err(Report::msg("transient failure"))
+            } else {
+                Ok(())
+            };
+
+            futures::future::ready(result)
+        }
+    }
+
+    /// A task that always fails, recording every attempt in `attempts`.
+    fn always_fails_task(attempts: Arc<AtomicU32>) -> impl Fn() -> futures::future::Ready<Result<(), Report>> {
+        move || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            futures::future::ready(Err(Report::msg("permanent failure")))
+        }
+    }
+
+    /// A task that exits with an error on its first call recovers and reports success once it
+    /// stops erroring, instead of carrying its earlier restart count into a permanent failure.
+    #[tokio::test(start_paused = true)]
+    async fn supervise_recovers_after_a_successful_restart() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            max_restarts: 3,
+            backoff: Duration::from_millis(10),
+        };
+
+        let result = supervise("flaky", policy, flaky_task(attempts.clone(), 1)).await;
+
+        assert!(result.is_ok());
+        // One failed attempt, then one successful attempt.
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    /// Each restart is preceded by `policy.backoff`, not issued immediately.
+    #[tokio::test(start_paused = true)]
+    async fn supervise_waits_backoff_between_restarts() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            max_restarts: 1,
+            backoff: Duration::from_secs(30),
+        };
+
+        let supervise_future = supervise("flaky", policy, flaky_task(attempts.clone(), 1));
+        tokio::pin!(supervise_future);
+
+        // The first attempt fails immediately, but the restart is gated on the backoff timer, so
+        // the supervised future doesn't resolve until time is advanced past it.
+        tokio::select! {
+            biased;
+            _ = &mut supervise_future => panic!("supervise resolved before its backoff elapsed"),
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        tokio::time::advance(policy.backoff).await;
+        let result = supervise_future.await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    /// A task that never succeeds is restarted exactly `max_restarts` times, then its failure is
+    /// surfaced as fatal instead of being retried forever.
+    #[tokio::test(start_paused = true)]
+    async fn supervise_gives_up_after_max_restarts() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            max_restarts: 3,
+            backoff: Duration::from_millis(10),
+        };
+
+        let result = supervise("doomed", policy, always_fails_task(attempts.clone())).await;
+
+        assert!(result.is_err());
+        // The initial attempt, plus exactly `max_restarts` retries.
+        assert_eq!(attempts.load(Ordering::SeqCst), policy.max_restarts + 1);
+    }
+}